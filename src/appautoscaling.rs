@@ -0,0 +1,106 @@
+use crate::AppResult;
+use crate::audit::Audit;
+use aws_sdk_applicationautoscaling::Client;
+use aws_sdk_applicationautoscaling::types::{ScalableDimension, ServiceNamespace, SuspendedState};
+use log::debug;
+use serde::Serialize;
+
+// Application Auto Scaling target of the desired count of an ECS service.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScalableTarget {
+    pub resource_id: String,
+    pub min_capacity: i32,
+    pub max_capacity: i32,
+    pub suspended: bool,
+}
+
+pub fn service_resource_id(cluster: &str, service_name: &str) -> String {
+    format!("service/{}/{}", cluster, service_name)
+}
+
+// Scalable targets of the services of the cluster.
+pub async fn list_service_scalable_targets(
+    client: &Client,
+    cluster: &str,
+) -> AppResult<Vec<ScalableTarget>> {
+    let prefix = service_resource_id(cluster, "");
+    let mut scalable_targets = Vec::new();
+    let mut scalable_targets_stream = client
+        .describe_scalable_targets()
+        .service_namespace(ServiceNamespace::Ecs)
+        .into_paginator()
+        .send();
+
+    while let Some(output) = scalable_targets_stream.next().await {
+        debug!("Scalable Targets: {:?}", output);
+        scalable_targets.extend(output?.scalable_targets().iter().filter_map(|target| {
+            let resource_id = target.resource_id()?;
+            if !resource_id.starts_with(&prefix) {
+                return None;
+            }
+            let suspended = target.suspended_state().is_some_and(|state| {
+                state.dynamic_scaling_in_suspended().unwrap_or_default()
+                    && state.dynamic_scaling_out_suspended().unwrap_or_default()
+                    && state.scheduled_scaling_suspended().unwrap_or_default()
+            });
+            Some(ScalableTarget {
+                resource_id: resource_id.to_owned(),
+                min_capacity: target.min_capacity().unwrap_or_default(),
+                max_capacity: target.max_capacity().unwrap_or_default(),
+                suspended,
+            })
+        }));
+    }
+    Ok(scalable_targets)
+}
+
+fn suspended_state(suspended: bool) -> SuspendedState {
+    SuspendedState::builder()
+        .dynamic_scaling_in_suspended(suspended)
+        .dynamic_scaling_out_suspended(suspended)
+        .scheduled_scaling_suspended(suspended)
+        .build()
+}
+
+async fn register_service_target(
+    client: &Client,
+    audit: &Audit,
+    resource_id: &str,
+    min_capacity: i32,
+    suspended: bool,
+) -> AppResult<()> {
+    audit
+        .record(
+            "application-autoscaling:RegisterScalableTarget",
+            &[
+                ("resource_id", resource_id),
+                ("min_capacity", &min_capacity.to_string()),
+                ("suspended", &suspended.to_string()),
+            ],
+            client
+                .register_scalable_target()
+                .service_namespace(ServiceNamespace::Ecs)
+                .resource_id(resource_id)
+                .scalable_dimension(ScalableDimension::EcsServiceDesiredCount)
+                .min_capacity(min_capacity)
+                .suspended_state(suspended_state(suspended))
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Suspends dynamic and scheduled scaling and lowers the min capacity to zero, so the
+// service is not scaled right back up once its desired count is zero.
+pub async fn suspend_scaling(client: &Client, audit: &Audit, resource_id: &str) -> AppResult<()> {
+    register_service_target(client, audit, resource_id, 0, true).await
+}
+
+pub async fn resume_scaling(
+    client: &Client,
+    audit: &Audit,
+    resource_id: &str,
+    min_capacity: i32,
+) -> AppResult<()> {
+    register_service_target(client, audit, resource_id, min_capacity, false).await
+}
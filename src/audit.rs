@@ -0,0 +1,307 @@
+use crate::AppResult;
+use crate::client::{SharedConfig, initialize_client};
+use crate::fanout::Target;
+use crate::output::Tabular;
+use aws_sdk_sts::Client as StsClient;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+// One line of the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub caller_arn: Option<String>,
+    pub account: Option<String>,
+    pub profile: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_arn: Option<String>,
+    pub region: String,
+    pub operation: String,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Tabular for AuditRecord {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "TIMESTAMP",
+            "CALLER",
+            "PROFILE",
+            "REGION",
+            "OPERATION",
+            "PARAMETERS",
+            "OUTCOME",
+            "ERROR",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        let profile = match &self.role_arn {
+            Some(role_arn) => format!("{}/{}", self.profile, role_arn),
+            None => self.profile.clone(),
+        };
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(",");
+        let outcome = match self.outcome {
+            Outcome::Success => "success",
+            Outcome::Failure => "failure",
+        };
+        vec![
+            self.timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            self.caller_arn.clone().unwrap_or_default(),
+            profile,
+            self.region.clone(),
+            self.operation.clone(),
+            parameters,
+            outcome.to_owned(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+#[derive(Debug, Default)]
+struct CallerIdentity {
+    arn: Option<String>,
+    account: Option<String>,
+}
+
+// Records the mutating calls made for one target. Without a log path calls are only run.
+#[derive(Clone)]
+pub struct Audit {
+    path: Option<PathBuf>,
+    target: Target,
+    sts_client: Option<StsClient>,
+    caller: Arc<OnceCell<CallerIdentity>>,
+}
+
+impl Audit {
+    pub fn new(path: Option<PathBuf>, target: &Target, shared_config: &SharedConfig) -> Self {
+        let sts_client = path
+            .as_ref()
+            .map(|_| initialize_client::<StsClient>(shared_config));
+        Self {
+            path,
+            target: target.clone(),
+            sts_client,
+            caller: Arc::new(OnceCell::new()),
+        }
+    }
+
+    // Only runs the calls, for library users without an audit log.
+    pub fn disabled() -> Self {
+        Self {
+            path: None,
+            target: Target {
+                profile: String::new(),
+                role_arn: None,
+                region: String::new(),
+            },
+            sts_client: None,
+            caller: Arc::new(OnceCell::new()),
+        }
+    }
+
+    // Looked up once per target, on the first recorded call.
+    async fn caller(&self) -> &CallerIdentity {
+        self.caller
+            .get_or_init(|| async {
+                let Some(client) = &self.sts_client else {
+                    return CallerIdentity::default();
+                };
+                match client.get_caller_identity().send().await {
+                    Ok(identity) => CallerIdentity {
+                        arn: identity.arn().map(ToOwned::to_owned),
+                        account: identity.account().map(ToOwned::to_owned),
+                    },
+                    Err(err) => {
+                        warn!("[{}] Cannot get caller identity: {}", self.target, err);
+                        CallerIdentity::default()
+                    }
+                }
+            })
+            .await
+    }
+
+    // Runs the call and appends its outcome to the audit log.
+    pub async fn record<T, E, Fut>(
+        &self,
+        operation: &str,
+        parameters: &[(&str, &str)],
+        call: Fut,
+    ) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+        E: Display,
+    {
+        let Some(path) = &self.path else {
+            return call.await;
+        };
+        let caller = self.caller().await;
+        let timestamp = Utc::now();
+        let result = call.await;
+
+        let record = AuditRecord {
+            timestamp,
+            caller_arn: caller.arn.clone(),
+            account: caller.account.clone(),
+            profile: self.target.profile.clone(),
+            role_arn: self.target.role_arn.clone(),
+            region: self.target.region.clone(),
+            operation: operation.to_owned(),
+            parameters: parameters
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            outcome: if result.is_ok() {
+                Outcome::Success
+            } else {
+                Outcome::Failure
+            },
+            error: result.as_ref().err().map(ToString::to_string),
+        };
+        // A failed write must not hide the outcome of a call which already happened.
+        if let Err(err) = append_record(path, &record) {
+            error!("Cannot write audit log {}: {}", path.display(), err);
+        }
+        result
+    }
+}
+
+pub fn default_audit_log() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state"))
+        })?;
+    Some(state_dir.join("aws-toolkit").join("audit.jsonl"))
+}
+
+fn append_record(path: &Path, record: &AuditRecord) -> AppResult<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    // One write per record, so records of concurrent runs do not interleave.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+// Every record of the log, an absent log has none.
+pub fn read_records(path: &Path) -> AppResult<Vec<AuditRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("Cannot read {}: {}", path.display(), err).into()),
+    };
+
+    let mut records = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => warn!(
+                "{}:{}: skipping record: {}",
+                path.display(),
+                number + 1,
+                err
+            ),
+        }
+    }
+    debug!("Read {} audit records", records.len());
+    Ok(records)
+}
+
+// Accepts a relative age like 30m, 12h or 7d, a date or an RFC 3339 timestamp.
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> AppResult<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    let invalid = || {
+        format!(
+            "Invalid time '{}', expected e.g. 30m, 12h, 7d or 2025-01-31",
+            value
+        )
+    };
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    let age = match unit {
+        'm' => Duration::minutes(amount),
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        _ => return Err(invalid().into()),
+    };
+    Ok(now - age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let now = DateTime::parse_from_rfc3339("2025-01-31T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_since("2h", now).unwrap().to_rfc3339(),
+            "2025-01-31T10:00:00+00:00"
+        );
+        assert_eq!(
+            parse_since("7d", now).unwrap().to_rfc3339(),
+            "2025-01-24T12:00:00+00:00"
+        );
+        assert_eq!(
+            parse_since("2025-01-01", now).unwrap().to_rfc3339(),
+            "2025-01-01T00:00:00+00:00"
+        );
+        assert!(parse_since("yesterday", now).is_err());
+        assert!(parse_since("", now).is_err());
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let line = r#"{"timestamp":"2025-01-31T12:00:00Z","caller_arn":null,"account":null,"profile":"prod","region":"eu-central-1","operation":"update_service","parameters":{"cluster":"app"},"outcome":"failure","error":"denied"}"#;
+        let record: AuditRecord = serde_json::from_str(line).unwrap();
+        assert_eq!(record.outcome, Outcome::Failure);
+        assert_eq!(record.parameters["cluster"], "app");
+        assert_eq!(serde_json::to_string(&record).unwrap(), line);
+    }
+}
@@ -1,12 +1,91 @@
 use crate::AppResult;
+use crate::audit::Audit;
+use crate::output::Tabular;
 use aws_sdk_autoscaling::Client;
+use aws_sdk_autoscaling::types::{AutoScalingGroup as SdkAutoScalingGroup, Tag};
 use log::debug;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AutoScalingGroup {
+    pub name: String,
+    pub desired_capacity: i32,
+    pub min_size: i32,
+    pub max_size: i32,
+    pub restore: Option<Capacity>,
+}
+
+// Capacity of a group from before it was scaled down.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Capacity {
+    pub min_size: i32,
+    pub max_size: i32,
+    pub desired_capacity: i32,
+}
+
+// Tag holding the capacity as `<min>,<max>,<desired>`.
+const CAPACITY_TAG: &str = "aws-toolkit:capacity";
+
+// Processes through which scaling policies and scheduled actions change the capacity,
+// including the target tracking policy of an ECS capacity provider with managed scaling.
+const SCALING_PROCESSES: [&str; 2] = ["AlarmNotification", "ScheduledActions"];
+
+impl Capacity {
+    fn parse(value: &str) -> Option<Self> {
+        let mut values = value.split(',').map(|value| value.trim().parse().ok());
+        let capacity = Self {
+            min_size: values.next()??,
+            max_size: values.next()??,
+            desired_capacity: values.next()??,
+        };
+        values.next().is_none().then_some(capacity)
+    }
+}
+
+impl std::fmt::Display for Capacity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            self.min_size, self.max_size, self.desired_capacity
+        )
+    }
+}
+
+impl Tabular for AutoScalingGroup {
+    fn headers() -> Vec<&'static str> {
+        vec!["ASG", "DESIRED", "MIN", "MAX"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.desired_capacity.to_string(),
+            self.min_size.to_string(),
+            self.max_size.to_string(),
+        ]
+    }
+}
+
+fn to_asg(asg: &SdkAutoScalingGroup) -> Option<AutoScalingGroup> {
+    Some(AutoScalingGroup {
+        name: asg.auto_scaling_group_name()?.to_owned(),
+        desired_capacity: asg.desired_capacity?,
+        min_size: asg.min_size.unwrap_or_default(),
+        max_size: asg.max_size.unwrap_or_default(),
+        restore: asg
+            .tags()
+            .iter()
+            .find(|tag| tag.key() == Some(CAPACITY_TAG))
+            .and_then(|tag| Capacity::parse(tag.value()?)),
+    })
+}
 
 pub async fn list_asgs(
     client: &Client,
     cluster: &str,
     desired_capacity: i32,
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<AutoScalingGroup>> {
     let mut asgs = Vec::new();
     let mut asg_stream = client
         .describe_auto_scaling_groups()
@@ -24,24 +103,169 @@ pub async fn list_asgs(
             if asg.desired_capacity?.eq(&desired_capacity) {
                 return None;
             }
-            Some(asg_name.to_owned())
+            to_asg(asg)
         }));
     }
     Ok(asgs)
 }
 
+// Groups by name, e.g. the groups of the capacity providers of a cluster.
+pub async fn describe_asgs(client: &Client, names: &[String]) -> AppResult<Vec<AutoScalingGroup>> {
+    let mut asgs = Vec::new();
+    for names_chunk in names.chunks(50) {
+        let output = client
+            .describe_auto_scaling_groups()
+            .set_auto_scaling_group_names(Some(names_chunk.to_vec()))
+            .send()
+            .await?;
+        debug!("ASG: {:?}", output);
+        asgs.extend(output.auto_scaling_groups().iter().filter_map(to_asg));
+    }
+    Ok(asgs)
+}
+
 pub async fn scale_down_asg(
     client: &Client,
+    audit: &Audit,
     asg_name: &str,
     desired_capacity: i32,
 ) -> AppResult<()> {
-    client
-        .update_auto_scaling_group()
-        .auto_scaling_group_name(asg_name)
-        .desired_capacity(desired_capacity)
-        .min_size(desired_capacity)
-        .max_size(desired_capacity)
-        .send()
+    let desired_capacity_string = desired_capacity.to_string();
+    audit
+        .record(
+            "autoscaling:UpdateAutoScalingGroup",
+            &[
+                ("auto_scaling_group_name", asg_name),
+                ("desired_capacity", &desired_capacity_string),
+            ],
+            client
+                .update_auto_scaling_group()
+                .auto_scaling_group_name(asg_name)
+                .desired_capacity(desired_capacity)
+                .min_size(desired_capacity)
+                .max_size(desired_capacity)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Remembers the capacity, so the group can be restored to it.
+pub async fn tag_capacity(
+    client: &Client,
+    audit: &Audit,
+    asg_name: &str,
+    capacity: Capacity,
+) -> AppResult<()> {
+    let capacity = capacity.to_string();
+    audit
+        .record(
+            "autoscaling:CreateOrUpdateTags",
+            &[
+                ("auto_scaling_group_name", asg_name),
+                (CAPACITY_TAG, &capacity),
+            ],
+            client
+                .create_or_update_tags()
+                .tags(
+                    Tag::builder()
+                        .resource_id(asg_name)
+                        .resource_type("auto-scaling-group")
+                        .key(CAPACITY_TAG)
+                        .value(&capacity)
+                        .propagate_at_launch(false)
+                        .build(),
+                )
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn suspend_scaling_processes(
+    client: &Client,
+    audit: &Audit,
+    asg_name: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "autoscaling:SuspendProcesses",
+            &[
+                ("auto_scaling_group_name", asg_name),
+                ("scaling_processes", &SCALING_PROCESSES.join(",")),
+            ],
+            client
+                .suspend_processes()
+                .auto_scaling_group_name(asg_name)
+                .set_scaling_processes(Some(SCALING_PROCESSES.map(String::from).to_vec()))
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn resume_scaling_processes(
+    client: &Client,
+    audit: &Audit,
+    asg_name: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "autoscaling:ResumeProcesses",
+            &[
+                ("auto_scaling_group_name", asg_name),
+                ("scaling_processes", &SCALING_PROCESSES.join(",")),
+            ],
+            client
+                .resume_processes()
+                .auto_scaling_group_name(asg_name)
+                .set_scaling_processes(Some(SCALING_PROCESSES.map(String::from).to_vec()))
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn restore_asg(
+    client: &Client,
+    audit: &Audit,
+    asg_name: &str,
+    capacity: Capacity,
+) -> AppResult<()> {
+    audit
+        .record(
+            "autoscaling:UpdateAutoScalingGroup",
+            &[
+                ("auto_scaling_group_name", asg_name),
+                ("capacity", &capacity.to_string()),
+            ],
+            client
+                .update_auto_scaling_group()
+                .auto_scaling_group_name(asg_name)
+                .min_size(capacity.min_size)
+                .max_size(capacity.max_size)
+                .desired_capacity(capacity.desired_capacity)
+                .send(),
+        )
         .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capacity_tag() {
+        let capacity = Capacity {
+            min_size: 1,
+            max_size: 4,
+            desired_capacity: 2,
+        };
+        assert_eq!(capacity.to_string(), "1,4,2");
+        assert_eq!(Capacity::parse("1,4,2"), Some(capacity));
+        assert_eq!(Capacity::parse("1,4"), None);
+        assert_eq!(Capacity::parse("1,4,2,0"), None);
+        assert_eq!(Capacity::parse("one,4,2"), None);
+    }
+}
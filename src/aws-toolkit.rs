@@ -0,0 +1,142 @@
+use aws_toolkit::{
+    AppResult,
+    cli::{
+        AUTHOR, EnvironmentDefaults, GlobalArgs, copy_rds_snapshots, ecr_gitconfig, history,
+        load_balancers, rds_snapshots, release_codepipelines, restore_elasticache, restore_rds,
+        s3_guardduty_copy, scale_in_ecs, ses_suppression_list, ssm_session, sweep_orphans,
+    },
+};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use clap_mangen::Man;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(version, long_about = None)]
+#[clap(author = AUTHOR, about = "Day-to-day operations on AWS")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    #[clap(flatten)]
+    global: GlobalArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = ssm_session::ABOUT)]
+    SsmSession(ssm_session::SsmSessionArgs),
+
+    #[command(about = scale_in_ecs::ABOUT)]
+    ScaleInEcs(scale_in_ecs::ScaleInEcsArgs),
+
+    #[command(about = ecr_gitconfig::ABOUT)]
+    EcrGitconfig(ecr_gitconfig::EcrGitconfigArgs),
+
+    #[command(about = ses_suppression_list::ABOUT)]
+    SesSuppressionList(ses_suppression_list::SesSuppressionListArgs),
+
+    #[command(about = s3_guardduty_copy::ABOUT)]
+    S3GuarddutyCopy(s3_guardduty_copy::S3GuarddutyCopyArgs),
+
+    #[command(about = release_codepipelines::ABOUT)]
+    ReleaseCodepipelines(release_codepipelines::ReleaseCodepipelinesArgs),
+
+    #[command(about = rds_snapshots::ABOUT)]
+    RdsSnapshots(rds_snapshots::RdsSnapshotsArgs),
+
+    #[command(about = copy_rds_snapshots::ABOUT)]
+    CopyRdsSnapshots(copy_rds_snapshots::CopyRdsSnapshotsArgs),
+
+    #[command(about = restore_elasticache::ABOUT)]
+    RestoreElasticache(restore_elasticache::RestoreElasticacheArgs),
+
+    #[command(about = restore_rds::ABOUT)]
+    RestoreRds(restore_rds::RestoreRdsArgs),
+
+    #[command(about = load_balancers::ABOUT)]
+    LoadBalancers(load_balancers::LoadBalancersArgs),
+
+    #[command(about = sweep_orphans::ABOUT)]
+    SweepOrphans(sweep_orphans::SweepOrphansArgs),
+
+    #[command(about = history::ABOUT)]
+    History(history::HistoryArgs),
+
+    #[command(about = "Prints shell completions to stdout")]
+    Completions {
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+
+    #[command(about = "Writes man pages for aws-toolkit and every subcommand")]
+    Manpages {
+        #[clap(default_value = "man")]
+        dir: PathBuf,
+    },
+}
+
+fn write_manpages(dir: &Path) -> AppResult<()> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut command = Cli::command();
+    command.build();
+
+    let name = command.get_name().to_owned();
+    Man::new(command.clone()).render(&mut File::create(dir.join(format!("{}.1", name)))?)?;
+
+    for subcommand in command.get_subcommands() {
+        let page = format!("{}-{}", name, subcommand.get_name());
+        let subcommand = subcommand.clone().name(page.clone());
+        Man::new(subcommand).render(&mut File::create(dir.join(format!("{}.1", page)))?)?;
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> AppResult<()> {
+    let mut cli = Cli::parse();
+    cli.global.init_logging();
+
+    let environment = cli.global.load_environment()?;
+    match &mut cli.command {
+        Command::SsmSession(args) => args.apply_environment(&environment),
+        Command::ScaleInEcs(args) => args.apply_environment(&environment),
+        Command::EcrGitconfig(args) => args.apply_environment(&environment),
+        Command::SesSuppressionList(args) => args.apply_environment(&environment),
+        Command::S3GuarddutyCopy(args) => args.apply_environment(&environment),
+        Command::ReleaseCodepipelines(args) => args.apply_environment(&environment),
+        Command::RdsSnapshots(args) => args.apply_environment(&environment),
+        Command::CopyRdsSnapshots(args) => args.apply_environment(&environment),
+        Command::RestoreElasticache(args) => args.apply_environment(&environment),
+        Command::RestoreRds(args) => args.apply_environment(&environment),
+        Command::LoadBalancers(args) => args.apply_environment(&environment),
+        Command::SweepOrphans(args) => args.apply_environment(&environment),
+        Command::History(args) => args.apply_environment(&environment),
+        Command::Completions { .. } | Command::Manpages { .. } => {}
+    }
+
+    match &cli.command {
+        Command::SsmSession(args) => ssm_session::run(&cli.global, args).await,
+        Command::ScaleInEcs(args) => scale_in_ecs::run(&cli.global, args).await,
+        Command::EcrGitconfig(args) => ecr_gitconfig::run(&cli.global, args).await,
+        Command::SesSuppressionList(args) => ses_suppression_list::run(&cli.global, args).await,
+        Command::S3GuarddutyCopy(args) => s3_guardduty_copy::run(&cli.global, args).await,
+        Command::ReleaseCodepipelines(args) => release_codepipelines::run(&cli.global, args).await,
+        Command::RdsSnapshots(args) => rds_snapshots::run(&cli.global, args).await,
+        Command::CopyRdsSnapshots(args) => copy_rds_snapshots::run(&cli.global, args).await,
+        Command::RestoreElasticache(args) => restore_elasticache::run(&cli.global, args).await,
+        Command::RestoreRds(args) => restore_rds::run(&cli.global, args).await,
+        Command::LoadBalancers(args) => load_balancers::run(&cli.global, args).await,
+        Command::SweepOrphans(args) => sweep_orphans::run(&cli.global, args).await,
+        Command::History(args) => history::run(&cli.global, args).await,
+        Command::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_owned();
+            clap_complete::generate(*shell, &mut command, name, &mut std::io::stdout());
+            Ok(())
+        }
+        Command::Manpages { dir } => write_manpages(dir),
+    }
+}
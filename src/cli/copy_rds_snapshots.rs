@@ -0,0 +1,359 @@
+use aws_sdk_rds::Client as RdsClient;
+use aws_sdk_sts::Client as StsClient;
+
+use crate::{
+    AppResult,
+    audit::Audit,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    fanout::{Fanout, Target, report_errors},
+    final_snapshot_name,
+    output::{self, Tabular, with_targets},
+    rds::{self, DbSnapshot},
+};
+use clap::Args;
+use futures::future::join_all;
+use log::warn;
+use serde::Serialize;
+use std::time::Duration;
+
+pub const ABOUT: &str = "Copy the latest RDS snapshots to another region or account";
+
+#[derive(Args, Debug, Clone)]
+pub struct CopyRdsSnapshotsArgs {
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub cluster: Option<String>,
+
+    #[clap(
+        long,
+        help = "Region the snapshots are copied to, defaults to the source region"
+    )]
+    pub destination_region: Option<String>,
+
+    #[clap(
+        long,
+        help = "Profile of the account the snapshots are copied to, defaults to the source profile"
+    )]
+    pub destination_profile: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "ROLE_ARN",
+        help = "Role assumed in the account the snapshots are copied to"
+    )]
+    pub destination_role: Option<String>,
+
+    #[clap(
+        long,
+        help = "KMS key in the destination the copies are encrypted with"
+    )]
+    pub kms_key_id: Option<String>,
+
+    #[clap(
+        long,
+        help = "KMS key in the source, shared with the destination account, encrypted snapshots are re-encrypted with before sharing"
+    )]
+    pub share_kms_key_id: Option<String>,
+
+    #[clap(
+        long,
+        default_value = "7200",
+        value_name = "SECONDS",
+        help = "How long to wait for a copy to become available"
+    )]
+    pub wait_timeout: u64,
+
+    #[clap(short, long)]
+    pub dry_run: bool,
+}
+
+impl EnvironmentDefaults for CopyRdsSnapshotsArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+impl CopyRdsSnapshotsArgs {
+    // The target copies of a source target go to, a destination profile drops the source role.
+    fn destination(&self, source: &Target) -> Target {
+        let role_arn = match (&self.destination_profile, &self.destination_role) {
+            (_, Some(role_arn)) => Some(role_arn.clone()),
+            (Some(_), None) => None,
+            (None, None) => source.role_arn.clone(),
+        };
+        Target {
+            profile: self
+                .destination_profile
+                .clone()
+                .unwrap_or_else(|| source.profile.clone()),
+            role_arn,
+            region: self
+                .destination_region
+                .clone()
+                .unwrap_or_else(|| source.region.clone()),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SnapshotCopy {
+    #[serde(flatten)]
+    pub snapshot: DbSnapshot,
+    pub destination: String,
+    pub copy: String,
+    pub status: Option<String>,
+    pub verified: bool,
+}
+
+impl Tabular for SnapshotCopy {
+    fn headers() -> Vec<&'static str> {
+        let mut headers = DbSnapshot::headers();
+        headers.extend(["DESTINATION", "COPY", "STATUS", "VERIFIED"]);
+        headers
+    }
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.snapshot.row();
+        row.extend([
+            self.destination.clone(),
+            self.copy.clone(),
+            self.status.clone().unwrap_or_default(),
+            self.verified.to_string(),
+        ]);
+        row
+    }
+}
+
+async fn account_id(shared_config: &SharedConfig) -> AppResult<String> {
+    let output = initialize_client::<StsClient>(shared_config)
+        .get_caller_identity()
+        .send()
+        .await?;
+    Ok(output.account().unwrap_or_default().to_owned())
+}
+
+// Clients and identity of the side of a copy.
+struct Side {
+    target: Target,
+    client: RdsClient,
+    audit: Audit,
+    account: String,
+}
+
+impl Side {
+    async fn new(
+        global: &GlobalArgs,
+        target: Target,
+        shared_config: SharedConfig,
+    ) -> AppResult<Self> {
+        Ok(Self {
+            client: initialize_client::<RdsClient>(&shared_config),
+            audit: global.audit(&target, &shared_config),
+            account: account_id(&shared_config).await?,
+            target,
+        })
+    }
+}
+
+// Copies one snapshot to the destination and waits for the copy. Snapshots are shared with
+// another account through a manual copy in the source region, as automated snapshots and
+// snapshots encrypted with the default RDS key cannot be shared.
+async fn copy_snapshot(
+    source: &Side,
+    destination: &Side,
+    args: &CopyRdsSnapshotsArgs,
+    snapshot: DbSnapshot,
+) -> AppResult<SnapshotCopy> {
+    let copy = final_snapshot_name(&format!("{}-dr", snapshot.source));
+    let mut record = SnapshotCopy {
+        destination: destination.target.to_string(),
+        copy,
+        status: None,
+        verified: false,
+        snapshot,
+    };
+    if args.dry_run {
+        return Ok(record);
+    }
+
+    let timeout = Duration::from_secs(args.wait_timeout);
+    let cluster_snapshot = record.snapshot.cluster_snapshot;
+    let source_arn = if source.account == destination.account {
+        record.snapshot.arn.clone()
+    } else {
+        let shared = final_snapshot_name(&format!("{}-share", record.snapshot.source));
+        eprintln!(
+            "[{}] Copying {} to {} for sharing",
+            source.target, record.snapshot.id, shared
+        );
+        let arn = rds::copy_snapshot(
+            &source.client,
+            &source.audit,
+            &record.snapshot.arn,
+            &shared,
+            cluster_snapshot,
+            args.share_kms_key_id.as_deref(),
+            None,
+        )
+        .await?;
+        let state =
+            rds::wait_for_snapshot_available(&source.client, &shared, cluster_snapshot, timeout)
+                .await?;
+        if !state.converged {
+            record.status = Some(format!("{} {}", shared, state.state));
+            return Ok(record);
+        }
+        rds::share_snapshot(
+            &source.client,
+            &source.audit,
+            &shared,
+            cluster_snapshot,
+            &destination.account,
+        )
+        .await?;
+        arn
+    };
+
+    eprintln!(
+        "[{}] Copying {} to {} in {}",
+        source.target, record.snapshot.id, record.copy, destination.target
+    );
+    let source_region = (source.target.region != destination.target.region)
+        .then_some(source.target.region.as_str());
+    rds::copy_snapshot(
+        &destination.client,
+        &destination.audit,
+        &source_arn,
+        &record.copy,
+        cluster_snapshot,
+        args.kms_key_id.as_deref(),
+        source_region,
+    )
+    .await?;
+    let state = rds::wait_for_snapshot_available(
+        &destination.client,
+        &record.copy,
+        cluster_snapshot,
+        timeout,
+    )
+    .await?;
+    record.status = Some(state.state);
+    record.verified = state.converged;
+    Ok(record)
+}
+
+async fn copy(
+    target: Target,
+    shared_config: SharedConfig,
+    global: &GlobalArgs,
+    destinations: &Fanout,
+    args: &CopyRdsSnapshotsArgs,
+    cluster: &str,
+) -> AppResult<Vec<SnapshotCopy>> {
+    let destination_target = args.destination(&target);
+    let destination_config = destinations.shared_config(&destination_target).await?;
+    let destination = Side::new(global, destination_target, destination_config).await?;
+    let source = Side::new(global, target, shared_config).await?;
+    if source.account == destination.account && source.target.region == destination.target.region {
+        return Err(format!(
+            "[{}] Destination is the source, give --destination-region or another account",
+            source.target
+        )
+        .into());
+    }
+
+    let mut sources: Vec<(String, bool)> = rds::list_db_instances(&source.client, cluster)
+        .await?
+        .into_iter()
+        .map(|db_instance| (db_instance.id, false))
+        .collect();
+    sources.extend(
+        rds::list_db_clusters(&source.client, cluster)
+            .await?
+            .into_iter()
+            .map(|db_cluster| (db_cluster.id, true)),
+    );
+
+    let mut snapshots = Vec::with_capacity(sources.len());
+    for (id, cluster_snapshot) in sources {
+        match rds::latest_snapshot(&source.client, &id, cluster_snapshot).await? {
+            Some(snapshot) => snapshots.push(snapshot),
+            None => warn!("[{}] {} has no available snapshot", source.target, id),
+        }
+    }
+
+    // Copies run at the same time, each one waits for itself.
+    join_all(
+        snapshots
+            .into_iter()
+            .map(|snapshot| copy_snapshot(&source, &destination, args, snapshot)),
+    )
+    .await
+    .into_iter()
+    .collect()
+}
+
+pub async fn run(global: &GlobalArgs, args: &CopyRdsSnapshotsArgs) -> AppResult<()> {
+    let cluster = args
+        .cluster
+        .as_deref()
+        .ok_or("--cluster is required, directly or through --env")?;
+
+    let fanout = global.fanout().await?;
+    let destinations = Fanout::with_targets(
+        fanout
+            .targets()
+            .iter()
+            .map(|target| args.destination(target))
+            .collect(),
+        &global.credentials,
+        &global.client,
+        1,
+    )
+    .await;
+    let lock = if args.dry_run {
+        None
+    } else {
+        global.lock("copy-rds-snapshots", &fanout).await?
+    };
+
+    let results = fanout
+        .run(|target, shared_config| {
+            copy(target, shared_config, global, &destinations, args, cluster)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let copies = with_targets(results?);
+    output::print(global.output, &copies)?;
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let pending: Vec<String> = copies
+        .iter()
+        .filter(|copy| !copy.item.verified)
+        .map(|copy| {
+            format!(
+                "{} ({})",
+                copy.item.copy,
+                copy.item.status.clone().unwrap_or_default()
+            )
+        })
+        .collect();
+    eprintln!(
+        "Verified {} copies, {} are not available.",
+        copies.len() - pending.len(),
+        pending.len()
+    );
+    if !pending.is_empty() {
+        return Err(format!("Not available: {}", pending.join(", ")).into());
+    }
+    Ok(())
+}
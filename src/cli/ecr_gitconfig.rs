@@ -0,0 +1,132 @@
+use crate::{
+    AppResult,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::initialize_client,
+    codecommit,
+    config::Environment,
+    fanout::{Fanout, Target, report_errors},
+    output::{self, TargetRecord},
+};
+use aws_sdk_codecommit::Client as CodeCommitClient;
+use clap::Args;
+use git2::{Config, ConfigLevel};
+use log::info;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+const DEFAULT_PROFILES: [&str; 1] = ["infra"];
+const DEFAULT_REGIONS: [&str; 2] = ["eu-central-1", "us-east-2"];
+
+pub const ABOUT: &str = "Updates custom gitconfig file";
+
+#[derive(Args, Debug, Clone)]
+pub struct EcrGitconfigArgs {
+    #[clap(short, long, default_value = "/tmp/gitconfig")]
+    pub file: String,
+
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub base: Vec<String>,
+
+    #[clap(short, long, default_value = None)]
+    pub include: Vec<String>,
+
+    #[clap(short = 'x', long, default_value = None)]
+    pub exclude: Vec<String>,
+
+    #[clap(short, long, default_value = None)]
+    pub email: Option<String>,
+
+    #[clap(short, long, default_value = None)]
+    pub name: Option<String>,
+}
+
+impl EnvironmentDefaults for EcrGitconfigArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.base.is_empty() {
+            self.base = environment.repos.base.clone();
+        }
+        if self.include.is_empty() {
+            self.include = environment.repos.include.clone();
+        }
+        if self.exclude.is_empty() {
+            self.exclude = environment.repos.exclude.clone();
+        }
+    }
+}
+
+fn write_gitconfig(file: &mut File, repo: &str, region: &str, profile: &str) -> AppResult<()> {
+    writeln!(
+        file,
+        "[credential \"https://git-codecommit.{}.amazonaws.com/v1/repos/{}.git\"]",
+        region, repo
+    )?;
+    writeln!(
+        file,
+        "\thelper = !aws codecommit credential-helper $@ --profile {}",
+        profile
+    )?;
+    writeln!(file, "\tuseHttpPath = true")?;
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, args: &EcrGitconfigArgs) -> AppResult<()> {
+    if args.base.is_empty() {
+        return Err("--base is required, directly or through --env".into());
+    }
+
+    let mut cfg = Config::open_default().unwrap();
+    let _ = cfg.add_file(Path::new(&args.file), ConfigLevel::Local, true);
+    let binding = cfg.get_string("user.name").unwrap();
+    let username = match &args.name {
+        Some(name) => name,
+        None => &binding,
+    };
+    let binding = cfg.get_string("user.email").unwrap();
+    let email = match &args.email {
+        Some(email) => email,
+        None => &binding,
+    };
+
+    let mut file = File::create(&args.file)?;
+
+    // Unlike the other commands, every profile and region is queried at once by default.
+    let targets = Target::matrix(
+        &global.targets.profiles_or(&DEFAULT_PROFILES),
+        &global.targets.roles,
+        &global.targets.regions_or(&DEFAULT_REGIONS),
+    );
+    let concurrency = targets.len();
+    let fanout =
+        Fanout::with_targets(targets, &global.credentials, &global.client, concurrency).await;
+
+    let results = fanout
+        .run(|_target, shared_config| async move {
+            let client = initialize_client::<CodeCommitClient>(&shared_config);
+            let base_repositories =
+                codecommit::list_exact_repositories(&client, &args.base, &args.exclude).await?;
+            info!("Base repositories: {:?}", base_repositories);
+            let repositories =
+                codecommit::list_repositories(&client, &args.include, &args.exclude).await?;
+            info!("Repositories: {:?}", repositories);
+            Ok((base_repositories, repositories))
+        })
+        .await;
+
+    let mut written = Vec::new();
+    for (target, (base_repositories, repositories)) in report_errors(results)? {
+        for repo in base_repositories.into_iter().chain(repositories) {
+            write_gitconfig(&mut file, &repo.name, &target.region, &target.profile)
+                .expect("Cannot write to file");
+            written.push(TargetRecord::new(&target, repo));
+        }
+    }
+
+    writeln!(
+        file,
+        "[credential]\n\thelper = !aws codecommit credential-helper $@\n\tUseHttpPath = true"
+    )?;
+    writeln!(file, "[user]\n\temail = {}\n\tname = {}", &email, &username)?;
+
+    output::print(global.output, &written)
+}
@@ -0,0 +1,66 @@
+use crate::{
+    AppResult,
+    audit::{Outcome, parse_since, read_records},
+    cli::{EnvironmentDefaults, GlobalArgs},
+    output,
+};
+use chrono::Utc;
+use clap::Args;
+
+pub const ABOUT: &str = "Shows mutating calls recorded in the audit log";
+
+#[derive(Args, Debug, Clone)]
+pub struct HistoryArgs {
+    #[clap(long, help = "Only calls newer than this, e.g. 12h, 7d or 2025-01-31")]
+    pub since: Option<String>,
+
+    #[clap(
+        long,
+        help = "Only operations containing this, e.g. DeleteDBInstance. Repeatable"
+    )]
+    pub operation: Vec<String>,
+
+    #[clap(long, help = "Only failed calls")]
+    pub failed: bool,
+
+    #[clap(short = 'n', long, help = "Show at most this many of the latest calls")]
+    pub limit: Option<usize>,
+}
+
+impl EnvironmentDefaults for HistoryArgs {}
+
+pub async fn run(global: &GlobalArgs, args: &HistoryArgs) -> AppResult<()> {
+    let path = global
+        .audit_log()
+        .ok_or("Cannot locate the audit log, pass --audit-log")?;
+    let since = args
+        .since
+        .as_deref()
+        .map(|since| parse_since(since, Utc::now()))
+        .transpose()?;
+
+    // Profiles and regions only filter when given, every target is shown otherwise.
+    let profiles = &global.targets.profiles;
+    let regions = &global.targets.regions;
+
+    let mut records: Vec<_> = read_records(&path)?
+        .into_iter()
+        .filter(|record| since.is_none_or(|since| record.timestamp >= since))
+        .filter(|record| {
+            args.operation.is_empty()
+                || args
+                    .operation
+                    .iter()
+                    .any(|operation| record.operation.contains(operation.as_str()))
+        })
+        .filter(|record| !args.failed || record.outcome == Outcome::Failure)
+        .filter(|record| profiles.is_empty() || profiles.contains(&record.profile))
+        .filter(|record| regions.is_empty() || regions.contains(&record.region))
+        .collect();
+
+    if let Some(limit) = args.limit {
+        records.drain(..records.len().saturating_sub(limit));
+    }
+
+    output::print(global.output, &records)
+}
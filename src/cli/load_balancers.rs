@@ -0,0 +1,246 @@
+use aws_sdk_elasticloadbalancingv2::Client as Elbv2Client;
+
+use crate::{
+    AppResult,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    elbv2::{self, LoadBalancerInventory},
+    fanout::report_errors,
+    output::{self, Tabular, with_targets},
+};
+use clap::Args;
+use serde::Serialize;
+
+pub const ABOUT: &str =
+    "Report listeners, rules, target groups and target health of load balancers";
+
+#[derive(Args, Debug, Clone)]
+pub struct LoadBalancersArgs {
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub cluster: Option<String>,
+
+    #[clap(short, long, help = "Show only targets which are not healthy")]
+    pub unhealthy: bool,
+}
+
+impl EnvironmentDefaults for LoadBalancersArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+// A target with the listener rules routing to its target group. Target groups without
+// targets and rules which do not forward anywhere get a row of their own.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TargetReport {
+    pub load_balancer: String,
+    pub routes: Vec<String>,
+    pub target_group: Option<String>,
+    pub target: Option<String>,
+    pub port: Option<i32>,
+    pub state: Option<String>,
+    pub reason: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Tabular for TargetReport {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "LOAD BALANCER",
+            "ROUTES",
+            "TARGET GROUP",
+            "TARGET",
+            "PORT",
+            "STATE",
+            "REASON",
+            "DESCRIPTION",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.load_balancer.clone(),
+            self.routes.join(", "),
+            self.target_group.clone().unwrap_or_default(),
+            self.target.clone().unwrap_or_default(),
+            self.port.map(|port| port.to_string()).unwrap_or_default(),
+            self.state.clone().unwrap_or_default(),
+            self.reason.clone().unwrap_or_default(),
+            self.description.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+fn reports(inventory: LoadBalancerInventory) -> Vec<TargetReport> {
+    let empty = TargetReport {
+        load_balancer: inventory.load_balancer.name,
+        routes: Vec::new(),
+        target_group: None,
+        target: None,
+        port: None,
+        state: None,
+        reason: None,
+        description: None,
+    };
+
+    let mut reports: Vec<TargetReport> = inventory
+        .rules
+        .iter()
+        .filter(|rule| rule.target_groups.is_empty())
+        .map(|rule| TargetReport {
+            routes: vec![format!("{} {}", rule.route(), rule.action)],
+            ..empty.clone()
+        })
+        .collect();
+
+    for target_group in inventory.target_groups {
+        let routes: Vec<String> = inventory
+            .rules
+            .iter()
+            .filter(|rule| rule.target_groups.contains(&target_group.arn))
+            .map(|rule| rule.route())
+            .collect();
+        let report = TargetReport {
+            routes,
+            target_group: Some(target_group.name),
+            ..empty.clone()
+        };
+        if target_group.targets.is_empty() {
+            reports.push(report);
+            continue;
+        }
+        reports.extend(target_group.targets.into_iter().map(|target| TargetReport {
+            target: Some(target.id),
+            port: target.port,
+            state: Some(target.state),
+            reason: target.reason,
+            description: target.description,
+            ..report.clone()
+        }));
+    }
+    reports
+}
+
+async fn inventory(
+    shared_config: SharedConfig,
+    args: &LoadBalancersArgs,
+    cluster: &str,
+) -> AppResult<Vec<TargetReport>> {
+    let client = initialize_client::<Elbv2Client>(&shared_config);
+    let load_balancers = elbv2::list_load_balancers(&client, cluster).await?;
+
+    let mut target_reports = Vec::new();
+    for load_balancer in load_balancers {
+        let inventory = elbv2::describe_inventory(&client, load_balancer).await?;
+        target_reports.extend(reports(inventory).into_iter().filter(|report| {
+            !args.unhealthy
+                || report
+                    .state
+                    .as_deref()
+                    .is_some_and(|state| state != "healthy")
+        }));
+    }
+    Ok(target_reports)
+}
+
+pub async fn run(global: &GlobalArgs, args: &LoadBalancersArgs) -> AppResult<()> {
+    let cluster = args
+        .cluster
+        .as_deref()
+        .ok_or("--cluster is required, directly or through --env")?;
+
+    let fanout = global.fanout().await?;
+    let results = fanout
+        .run(|_, shared_config| inventory(shared_config, args, cluster))
+        .await;
+
+    let reports = with_targets(report_errors(results)?);
+    output::print(global.output, &reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elbv2::{ListenerRule, LoadBalancer, TargetGroup, TargetHealth};
+
+    #[test]
+    fn test_reports() {
+        let target_group_arn = "targetgroup/app-api/1".to_owned();
+        let inventory = LoadBalancerInventory {
+            load_balancer: LoadBalancer {
+                name: "app-alb".to_owned(),
+                arn: "loadbalancer/app/app-alb/1".to_owned(),
+                dns_name: None,
+            },
+            rules: vec![
+                ListenerRule {
+                    listener: "HTTP:80".to_owned(),
+                    priority: "default".to_owned(),
+                    conditions: Vec::new(),
+                    action: "redirect".to_owned(),
+                    target_groups: Vec::new(),
+                },
+                ListenerRule {
+                    listener: "HTTPS:443".to_owned(),
+                    priority: "default".to_owned(),
+                    conditions: Vec::new(),
+                    action: "forward".to_owned(),
+                    target_groups: vec![target_group_arn.clone()],
+                },
+            ],
+            target_groups: vec![
+                TargetGroup {
+                    name: "app-api".to_owned(),
+                    arn: target_group_arn,
+                    protocol: Some("HTTP".to_owned()),
+                    port: Some(8080),
+                    targets: vec![TargetHealth {
+                        id: "10.0.1.10".to_owned(),
+                        port: Some(8080),
+                        state: "unhealthy".to_owned(),
+                        reason: Some("Target.Timeout".to_owned()),
+                        description: None,
+                    }],
+                },
+                TargetGroup {
+                    name: "app-old".to_owned(),
+                    arn: "targetgroup/app-old/1".to_owned(),
+                    protocol: Some("HTTP".to_owned()),
+                    port: Some(8080),
+                    targets: Vec::new(),
+                },
+            ],
+        };
+
+        let rows: Vec<Vec<String>> = reports(inventory).iter().map(Tabular::row).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec![
+                    "app-alb",
+                    "HTTP:80 default redirect",
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",
+                    ""
+                ],
+                vec![
+                    "app-alb",
+                    "HTTPS:443 default",
+                    "app-api",
+                    "10.0.1.10",
+                    "8080",
+                    "unhealthy",
+                    "Target.Timeout",
+                    ""
+                ],
+                vec!["app-alb", "", "app-old", "", "", "", "", ""],
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+pub mod copy_rds_snapshots;
+pub mod ecr_gitconfig;
+pub mod history;
+pub mod load_balancers;
+pub mod rds_snapshots;
+pub mod release_codepipelines;
+pub mod restore_elasticache;
+pub mod restore_rds;
+pub mod s3_guardduty_copy;
+pub mod scale_in_ecs;
+pub mod ses_suppression_list;
+pub mod ssm_session;
+pub mod sweep_orphans;
+
+use crate::AppResult;
+use crate::audit::{Audit, default_audit_log};
+use crate::client::{ClientArgs, SharedConfig, initialize_client};
+use crate::config::{ConfigFile, Environment};
+use crate::credentials::CredentialArgs;
+use crate::fanout::{Fanout, Target, TargetArgs};
+use crate::lock::{LockArgs, RunLock, lock_name};
+use crate::output::OutputFormat;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use clap::{ArgAction, Args};
+use std::path::PathBuf;
+
+pub const AUTHOR: &str = "Anton Sidorov tonysidrock@gmail.com";
+
+// Flags shared by every command, accepted before or after the subcommand name.
+#[derive(Args, Debug, Clone)]
+pub struct GlobalArgs {
+    #[clap(flatten)]
+    pub targets: TargetArgs,
+
+    #[clap(flatten)]
+    pub credentials: CredentialArgs,
+
+    #[clap(flatten)]
+    pub client: ClientArgs,
+
+    #[clap(flatten)]
+    pub lock: LockArgs,
+
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        default_value = "text",
+        help = "Output format of the results"
+    )]
+    pub output: OutputFormat,
+
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_TOOLKIT_CONFIG",
+        help = "Config file used instead of the project-local aws-toolkit.toml"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_TOOLKIT_ENV",
+        help = "Named environment from the config files"
+    )]
+    pub env: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_TOOLKIT_AUDIT_LOG",
+        help = "Audit log of mutating calls [default: ~/.local/state/aws-toolkit/audit.jsonl]"
+    )]
+    pub audit_log: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Do not record mutating calls in the audit log"
+    )]
+    pub no_audit: bool,
+
+    #[clap(
+        short,
+        long,
+        global = true,
+        action = ArgAction::Count,
+        help = "Increase logging verbosity, repeatable. RUST_LOG takes precedence"
+    )]
+    pub verbose: u8,
+}
+
+impl GlobalArgs {
+    pub fn init_logging(&self) {
+        let level = match self.verbose {
+            0 => "error",
+            1 => "info",
+            2 => "debug",
+            _ => "trace",
+        };
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
+    }
+
+    // Fills targets and role left unset on the command line from the selected environment.
+    pub fn load_environment(&mut self) -> AppResult<Environment> {
+        let config = ConfigFile::load(self.config.as_deref())?;
+        let environment = config.environment(self.env.as_deref())?;
+
+        if self.targets.profiles.is_empty() {
+            self.targets.profiles = environment.profiles.clone();
+        }
+        if self.targets.regions.is_empty() {
+            self.targets.regions = environment.regions.clone();
+        }
+        if self.targets.roles.is_empty() && self.credentials.role_arn.is_none() {
+            self.credentials.role_arn = environment.role_arn.clone();
+        }
+        if self.lock.lock_table.is_none() {
+            self.lock.lock_table = environment.lock_table.clone();
+        }
+        Ok(environment)
+    }
+
+    pub fn audit_log(&self) -> Option<PathBuf> {
+        self.audit_log.clone().or_else(default_audit_log)
+    }
+
+    pub fn audit(&self, target: &Target, shared_config: &SharedConfig) -> Audit {
+        let path = if self.no_audit {
+            None
+        } else {
+            self.audit_log()
+        };
+        Audit::new(path, target, shared_config)
+    }
+
+    pub async fn fanout(&self) -> AppResult<Fanout> {
+        Fanout::new(&self.targets, &self.credentials, &self.client).await
+    }
+
+    // Locks the environment, or the targets without one, for a destructive command.
+    // The lock table is read through the first target, nothing is locked without a table.
+    pub async fn lock(&self, command: &str, fanout: &Fanout) -> AppResult<Option<RunLock>> {
+        let Some(table) = &self.lock.lock_table else {
+            return Ok(None);
+        };
+        let first = fanout
+            .targets()
+            .first()
+            .ok_or("No profile or region given")?;
+        let shared_config = fanout.shared_config(first).await?;
+        let client = initialize_client::<DynamoDbClient>(&shared_config);
+        let name = lock_name(self.env.as_deref(), fanout.targets());
+        RunLock::acquire(client, &self.lock, table, &name, command)
+            .await
+            .map(Some)
+    }
+}
+
+// Command arguments which fall back to values of the selected environment.
+pub trait EnvironmentDefaults {
+    fn apply_environment(&mut self, _environment: &Environment) {}
+}
@@ -0,0 +1,140 @@
+use aws_sdk_rds::Client as RdsClient;
+
+use crate::{
+    AppResult,
+    audit::Audit,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    fanout::{Target, report_errors},
+    output::{self, Tabular, with_targets},
+    rds::{self, DbSnapshot, Retention},
+};
+use chrono::Utc;
+use clap::Args;
+use serde::Serialize;
+
+pub const ABOUT: &str = "List and prune manual RDS snapshots";
+
+#[derive(Args, Debug, Clone)]
+pub struct RdsSnapshotsArgs {
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub cluster: Option<String>,
+
+    #[clap(
+        long,
+        default_value = "3",
+        help = "Newest snapshots kept per instance or cluster"
+    )]
+    pub keep_last: usize,
+
+    #[clap(long, value_name = "DAYS", help = "Keep snapshots newer than this")]
+    pub keep_days: Option<i64>,
+
+    #[clap(long, help = "Delete the snapshots past the retention")]
+    pub prune: bool,
+
+    #[clap(short, long, requires = "prune")]
+    pub dry_run: bool,
+}
+
+impl EnvironmentDefaults for RdsSnapshotsArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SnapshotRetention {
+    #[serde(flatten)]
+    pub snapshot: DbSnapshot,
+    pub age_days: i64,
+    pub action: &'static str,
+}
+
+impl Tabular for SnapshotRetention {
+    fn headers() -> Vec<&'static str> {
+        let mut headers = DbSnapshot::headers();
+        headers.extend(["AGE DAYS", "ACTION"]);
+        headers
+    }
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.snapshot.row();
+        row.extend([self.age_days.to_string(), self.action.to_owned()]);
+        row
+    }
+}
+
+async fn snapshots(
+    target: Target,
+    shared_config: SharedConfig,
+    audit: Audit,
+    args: &RdsSnapshotsArgs,
+    cluster: &str,
+) -> AppResult<Vec<SnapshotRetention>> {
+    let client = initialize_client::<RdsClient>(&shared_config);
+    let mut snapshots = rds::list_manual_snapshots(&client, cluster).await?;
+    snapshots.sort_by(|a, b| a.source.cmp(&b.source).then(b.created.cmp(&a.created)));
+
+    let now = Utc::now();
+    let retention = Retention {
+        keep_last: args.keep_last,
+        keep_days: args.keep_days,
+    };
+    let expired: Vec<String> = retention
+        .expired(&snapshots, now)
+        .into_iter()
+        .map(|snapshot| snapshot.id.clone())
+        .collect();
+
+    let mut retentions = Vec::with_capacity(snapshots.len());
+    for snapshot in snapshots {
+        let action = if !expired.contains(&snapshot.id) {
+            "keep"
+        } else if args.prune && !args.dry_run {
+            eprintln!("[{}] Deleting {}", target, snapshot.id);
+            rds::delete_snapshot(&client, &audit, &snapshot).await?;
+            "deleted"
+        } else {
+            "expired"
+        };
+        retentions.push(SnapshotRetention {
+            age_days: (now - snapshot.created).num_days(),
+            snapshot,
+            action,
+        });
+    }
+
+    Ok(retentions)
+}
+
+pub async fn run(global: &GlobalArgs, args: &RdsSnapshotsArgs) -> AppResult<()> {
+    let cluster = args
+        .cluster
+        .as_deref()
+        .ok_or("--cluster is required, directly or through --env")?;
+
+    let fanout = global.fanout().await?;
+    let lock = if args.prune && !args.dry_run {
+        global.lock("rds-snapshots", &fanout).await?
+    } else {
+        None
+    };
+
+    let results = fanout
+        .run(|target, shared_config| {
+            let audit = global.audit(&target, &shared_config);
+            snapshots(target, shared_config, audit, args, cluster)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let retentions = with_targets(results?);
+    output::print(global.output, &retentions)
+}
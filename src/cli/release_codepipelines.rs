@@ -0,0 +1,134 @@
+use aws_sdk_codepipeline::Client as CodepipelineClient;
+
+use crate::{
+    AppResult,
+    audit::Audit,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    codepipeline::{self, Pipeline},
+    config::Environment,
+    fanout::{Target, report_errors},
+    output::{self, Tabular, with_targets},
+};
+use clap::Args;
+use serde::Serialize;
+
+pub const ABOUT: &str = "Release codepipeline";
+
+#[derive(Args, Debug, Clone)]
+pub struct ReleaseCodepipelinesArgs {
+    #[clap(short = 'm', long, default_value = None)]
+    pub prefix_match: Vec<String>,
+
+    #[clap(short = 'x', long, default_value = None)]
+    pub prefix_exclude: Vec<String>,
+
+    #[clap(short, long)]
+    pub failed_only: bool,
+
+    #[clap(short, long, conflicts_with = "failed_only")]
+    pub all: bool,
+
+    #[clap(short, long)]
+    pub dry_run: bool,
+}
+
+impl EnvironmentDefaults for ReleaseCodepipelinesArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.prefix_match.is_empty() {
+            self.prefix_match = environment.pipelines.include.clone();
+        }
+        if self.prefix_exclude.is_empty() {
+            self.prefix_exclude = environment.pipelines.exclude.clone();
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PipelineRelease {
+    #[serde(flatten)]
+    pub pipeline: Pipeline,
+    pub released: bool,
+}
+
+impl Tabular for PipelineRelease {
+    fn headers() -> Vec<&'static str> {
+        let mut headers = Pipeline::headers();
+        headers.push("RELEASED");
+        headers
+    }
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.pipeline.row();
+        row.push(self.released.to_string());
+        row
+    }
+}
+
+async fn release(
+    target: Target,
+    shared_config: SharedConfig,
+    audit: Audit,
+    args: &ReleaseCodepipelinesArgs,
+) -> AppResult<Vec<PipelineRelease>> {
+    let codepipeline_client = initialize_client::<CodepipelineClient>(&shared_config);
+    let pipelines = if args.failed_only {
+        codepipeline::list_failed_pipelines(
+            &codepipeline_client,
+            &args.prefix_match,
+            &args.prefix_exclude,
+        )
+        .await?
+    } else if args.all {
+        codepipeline::list_all_pipelines(
+            &codepipeline_client,
+            &args.prefix_match,
+            &args.prefix_exclude,
+        )
+        .await?
+    } else {
+        codepipeline::list_pipelines(
+            &codepipeline_client,
+            &args.prefix_match,
+            &args.prefix_exclude,
+        )
+        .await?
+    };
+
+    let mut releases = Vec::with_capacity(pipelines.len());
+    for pipeline in pipelines {
+        if !args.dry_run {
+            eprintln!("[{}] Releasing {}", target, pipeline.name);
+            codepipeline::release_pipeline(&codepipeline_client, &audit, &pipeline.name).await?;
+        }
+        releases.push(PipelineRelease {
+            pipeline,
+            released: !args.dry_run,
+        });
+    }
+
+    Ok(releases)
+}
+
+pub async fn run(global: &GlobalArgs, args: &ReleaseCodepipelinesArgs) -> AppResult<()> {
+    let fanout = global.fanout().await?;
+    let lock = if args.dry_run {
+        None
+    } else {
+        global.lock("release-codepipelines", &fanout).await?
+    };
+
+    let results = fanout
+        .run(|target, shared_config| {
+            let audit = global.audit(&target, &shared_config);
+            release(target, shared_config, audit, args)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let releases = with_targets(results?);
+    output::print(global.output, &releases)
+}
@@ -0,0 +1,119 @@
+use aws_sdk_elasticache::Client as ElasticacheClient;
+
+use crate::{
+    AppResult,
+    audit::Audit,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    elasticache::{self, HibernatedGroup},
+    fanout::{Target, report_errors},
+    output::{self, Tabular, with_targets},
+};
+use clap::Args;
+use log::info;
+use serde::Serialize;
+
+pub const ABOUT: &str = "Recreate ElastiCache replication groups hibernated by scale-in-ecs";
+
+#[derive(Args, Debug, Clone)]
+pub struct RestoreElasticacheArgs {
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub cluster: Option<String>,
+
+    #[clap(short, long)]
+    pub dry_run: bool,
+}
+
+impl EnvironmentDefaults for RestoreElasticacheArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CacheRestore {
+    #[serde(flatten)]
+    pub group: HibernatedGroup,
+    pub restored: bool,
+}
+
+impl Tabular for CacheRestore {
+    fn headers() -> Vec<&'static str> {
+        let mut headers = HibernatedGroup::headers();
+        headers.push("RESTORED");
+        headers
+    }
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.group.row();
+        row.push(self.restored.to_string());
+        row
+    }
+}
+
+async fn restore(
+    target: Target,
+    shared_config: SharedConfig,
+    audit: Audit,
+    args: &RestoreElasticacheArgs,
+    cluster: &str,
+) -> AppResult<Vec<CacheRestore>> {
+    let client = initialize_client::<ElasticacheClient>(&shared_config);
+    let hibernated = elasticache::list_hibernated_groups(&client, cluster).await?;
+
+    let mut restores = Vec::with_capacity(hibernated.len());
+    for group in hibernated {
+        // Groups restored before keep their snapshot, they are left alone.
+        if elasticache::replication_group_exists(&client, &group.id).await? {
+            info!(
+                "[{}] {} exists, skipping {}",
+                target, group.id, group.snapshot
+            );
+            continue;
+        }
+        if !args.dry_run {
+            eprintln!(
+                "[{}] Restoring {} from {}",
+                target, group.id, group.snapshot
+            );
+            elasticache::restore_replication_group(&client, &audit, &group).await?;
+        }
+        restores.push(CacheRestore {
+            group,
+            restored: !args.dry_run,
+        });
+    }
+
+    Ok(restores)
+}
+
+pub async fn run(global: &GlobalArgs, args: &RestoreElasticacheArgs) -> AppResult<()> {
+    let cluster = args
+        .cluster
+        .as_deref()
+        .ok_or("--cluster is required, directly or through --env")?;
+
+    let fanout = global.fanout().await?;
+    let lock = if args.dry_run {
+        None
+    } else {
+        global.lock("restore-elasticache", &fanout).await?
+    };
+
+    let results = fanout
+        .run(|target, shared_config| {
+            let audit = global.audit(&target, &shared_config);
+            restore(target, shared_config, audit, args, cluster)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let restores = with_targets(results?);
+    output::print(global.output, &restores)
+}
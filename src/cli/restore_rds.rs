@@ -0,0 +1,135 @@
+use aws_sdk_rds::Client as RdsClient;
+
+use crate::{
+    AppResult,
+    audit::Audit,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    fanout::{Target, report_errors},
+    output::{self, Tabular, with_targets},
+    rds::{self, RestorableInstance},
+};
+use clap::Args;
+use log::info;
+use serde::Serialize;
+use std::time::Duration;
+
+pub const ABOUT: &str = "Recreate RDS instances deleted by scale-in-ecs from their final snapshot";
+
+#[derive(Args, Debug, Clone)]
+pub struct RestoreRdsArgs {
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub cluster: Option<String>,
+
+    #[clap(
+        long,
+        default_value = "3600",
+        value_name = "SECONDS",
+        help = "How long to wait for a restored instance to become available"
+    )]
+    pub wait_timeout: u64,
+
+    #[clap(short, long)]
+    pub dry_run: bool,
+}
+
+impl EnvironmentDefaults for RestoreRdsArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InstanceRestore {
+    #[serde(flatten)]
+    pub instance: RestorableInstance,
+    pub status: Option<String>,
+}
+
+impl Tabular for InstanceRestore {
+    fn headers() -> Vec<&'static str> {
+        let mut headers = RestorableInstance::headers();
+        headers.push("STATUS");
+        headers
+    }
+
+    fn row(&self) -> Vec<String> {
+        let mut row = self.instance.row();
+        row.push(self.status.clone().unwrap_or_default());
+        row
+    }
+}
+
+async fn restore(
+    target: Target,
+    shared_config: SharedConfig,
+    audit: Audit,
+    args: &RestoreRdsArgs,
+    cluster: &str,
+) -> AppResult<Vec<InstanceRestore>> {
+    let client = initialize_client::<RdsClient>(&shared_config);
+    let restorable = rds::list_restorable_instances(&client, cluster).await?;
+
+    let mut restores = Vec::with_capacity(restorable.len());
+    for instance in restorable {
+        if rds::db_instance_exists(&client, &instance.id).await? {
+            info!(
+                "[{}] {} exists, skipping {}",
+                target, instance.id, instance.snapshot
+            );
+            continue;
+        }
+        let status = if args.dry_run {
+            None
+        } else {
+            eprintln!(
+                "[{}] Restoring {} from {}",
+                target, instance.id, instance.snapshot
+            );
+            let timeout = Duration::from_secs(args.wait_timeout);
+            let state = rds::restore_db_instance(&client, &audit, &instance, timeout).await?;
+            if !state.converged {
+                return Err(format!(
+                    "[{}] {} is {} after {} s",
+                    target, instance.id, state.state, args.wait_timeout
+                )
+                .into());
+            }
+            Some(state.state)
+        };
+        restores.push(InstanceRestore { instance, status });
+    }
+
+    Ok(restores)
+}
+
+pub async fn run(global: &GlobalArgs, args: &RestoreRdsArgs) -> AppResult<()> {
+    let cluster = args
+        .cluster
+        .as_deref()
+        .ok_or("--cluster is required, directly or through --env")?;
+
+    let fanout = global.fanout().await?;
+    let lock = if args.dry_run {
+        None
+    } else {
+        global.lock("restore-rds", &fanout).await?
+    };
+
+    let results = fanout
+        .run(|target, shared_config| {
+            let audit = global.audit(&target, &shared_config);
+            restore(target, shared_config, audit, args, cluster)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let restores = with_targets(results?);
+    output::print(global.output, &restores)
+}
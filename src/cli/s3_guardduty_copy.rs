@@ -0,0 +1,112 @@
+use aws_sdk_guardduty::Client as GDClient;
+use aws_sdk_s3::Client as S3Client;
+use log::debug;
+
+use crate::{
+    AppResult,
+    audit::Audit,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    fanout::{Target, report_errors},
+    guardduty,
+    output::{self, with_targets},
+    s3::{self, BucketCopy},
+};
+use clap::Args;
+
+pub const ABOUT: &str = "Copies S3 objects in place so GuardDuty malware protection scans them";
+
+#[derive(Args, Debug, Clone)]
+pub struct S3GuarddutyCopyArgs {
+    #[clap(short = 'm', long, help = "Matches every bucket by default")]
+    pub prefix_match: Vec<String>,
+
+    #[clap(short = 'x', long, default_value = None)]
+    pub prefix_exclude: Vec<String>,
+
+    #[clap(short, long)]
+    pub all: bool,
+
+    #[clap(short, long)]
+    pub dry_run: bool,
+}
+
+impl EnvironmentDefaults for S3GuarddutyCopyArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.prefix_match.is_empty() {
+            self.prefix_match = environment.buckets.include.clone();
+        }
+        if self.prefix_exclude.is_empty() {
+            self.prefix_exclude = environment.buckets.exclude.clone();
+        }
+    }
+}
+
+async fn copy(
+    target: Target,
+    shared_config: SharedConfig,
+    audit: Audit,
+    args: &S3GuarddutyCopyArgs,
+) -> AppResult<Vec<BucketCopy>> {
+    let s3_client = initialize_client::<S3Client>(&shared_config);
+    let gd_client = initialize_client::<GDClient>(&shared_config);
+
+    // An empty prefix is contained in every bucket name.
+    let prefix_match = if args.prefix_match.is_empty() {
+        vec![String::new()]
+    } else {
+        args.prefix_match.clone()
+    };
+
+    let bucket_names: Vec<String> = if args.all {
+        s3::get_buckets(&s3_client, &prefix_match, &args.prefix_exclude)
+            .await?
+            .into_iter()
+            .map(|bucket| bucket.name)
+            .collect()
+    } else {
+        guardduty::list_malware_protected_buckets(&gd_client, &prefix_match, &args.prefix_exclude)
+            .await?
+            .into_iter()
+            .map(|bucket| bucket.name)
+            .collect()
+    };
+    debug!("[{}] {:?}", target, bucket_names);
+
+    let mut copies = Vec::with_capacity(bucket_names.len());
+    for bucket in bucket_names {
+        eprintln!("[{}] Bucket {}", target, &bucket);
+        let bucket_policy = s3::save_bucket_policy(&s3_client, &bucket).await?;
+        if bucket_policy.is_some() {
+            s3::delete_bucket_policy(&s3_client, &audit, &bucket).await?;
+        }
+
+        let copy = s3::copy_all_objects(&s3_client, &audit, &bucket).await?;
+        if let Some(policy) = bucket_policy {
+            s3::restore_bucket_policy(&s3_client, &audit, &bucket, &policy).await?;
+        }
+        copies.push(copy);
+    }
+
+    Ok(copies)
+}
+
+pub async fn run(global: &GlobalArgs, args: &S3GuarddutyCopyArgs) -> AppResult<()> {
+    let fanout = global.fanout().await?;
+    let lock = global.lock("s3-guardduty-copy", &fanout).await?;
+
+    let results = fanout
+        .run(|target, shared_config| {
+            let audit = global.audit(&target, &shared_config);
+            copy(target, shared_config, audit, args)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let copies = with_targets(results?);
+    output::print(global.output, &copies)
+}
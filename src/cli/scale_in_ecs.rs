@@ -0,0 +1,1469 @@
+use aws_sdk_applicationautoscaling::Client as AppAutoScalingClient;
+use aws_sdk_autoscaling::Client as AutoScalingClient;
+use aws_sdk_ec2::Client as Ec2Client;
+use aws_sdk_ecs::Client as EcsClient;
+use aws_sdk_elasticache::Client as ElasticacheClient;
+use aws_sdk_elasticloadbalancingv2::Client as Elbv2Client;
+use aws_sdk_rds::Client as RdsClient;
+
+use crate::{
+    AppResult,
+    appautoscaling::{self, ScalableTarget},
+    audit::Audit,
+    autoscaling::{self, AutoScalingGroup, Capacity},
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    ec2::{self, Ec2Instance, InstancePolicy, NatGateway, NatGatewayRecord},
+    ecs::{self, EcsService},
+    elasticache::{self, CacheCluster, ReplicationGroup, ServerlessCache},
+    elbv2::{self, LoadBalancer, TargetGroup},
+    fanout::{Target, report_errors},
+    output::{self, Tabular, with_targets},
+    rds::{self, DbCluster, DbInstance},
+    wait::ResourceState,
+};
+use clap::Args;
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use log::{debug, info};
+use serde::Serialize;
+use std::future::Future;
+use std::io::Write;
+use std::time::Duration;
+
+pub const ABOUT: &str = "Scale down ECS cluster";
+
+#[derive(Args, Debug, Clone)]
+pub struct ScaleInEcsArgs {
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub cluster: Option<String>,
+
+    #[clap(short, long, default_value = "false", conflicts_with = "migration")]
+    pub delete: bool,
+
+    #[clap(short, long, default_value = "false")]
+    pub migration: bool,
+
+    #[clap(short = 'f', long, default_value = "false")]
+    pub skip_final_rds_snapshot: bool,
+
+    #[clap(short, long, default_value = "false", conflicts_with = "migration")]
+    pub scaledown: bool,
+
+    #[clap(
+        long,
+        conflicts_with_all = ["delete", "scaledown", "migration"],
+        help = "Scale services back up to their desired count before the last scale down and remove maintenance rules"
+    )]
+    pub scaleup: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "scaleup",
+        help = "Deregister the targets of services and wait out the deregistration delay before scaling them down"
+    )]
+    pub drain: bool,
+
+    #[clap(
+        long,
+        requires = "drain",
+        help = "Answer every request to the load balancers with 503 until --scaleup"
+    )]
+    pub maintenance: bool,
+
+    #[clap(
+        long,
+        conflicts_with_all = ["migration", "scaleup"],
+        help = "Release the Elastic IPs of deleted NAT gateways, --scaleup allocates new ones"
+    )]
+    pub release_eips: bool,
+
+    #[clap(
+        long,
+        requires = "scaledown",
+        help = "Delete ElastiCache replication groups with a snapshot, restore-elasticache recreates them"
+    )]
+    pub hibernate_caches: bool,
+
+    #[clap(
+        long,
+        requires = "delete",
+        help = "Take a final snapshot of deleted Redis and Valkey caches"
+    )]
+    pub final_cache_snapshot: bool,
+
+    #[clap(
+        long,
+        default_value = "8",
+        help = "Resources of one kind changed at once within a target"
+    )]
+    pub max_parallel: usize,
+
+    #[clap(
+        short,
+        long,
+        help = "Wait until every change took effect and report the verified state"
+    )]
+    pub wait: bool,
+
+    #[clap(
+        long,
+        default_value = "1800",
+        value_name = "SECONDS",
+        help = "How long a step waits for its changes to take effect"
+    )]
+    pub wait_timeout: u64,
+}
+
+impl EnvironmentDefaults for ScaleInEcsArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+// A resource of the cluster and what was done to it, `found` when it was only listed.
+// The state is the one observed after waiting for the action to take effect.
+#[derive(Serialize, Debug, Clone)]
+pub struct ResourceAction {
+    pub resource: &'static str,
+    pub id: String,
+    pub action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub converged: Option<bool>,
+}
+
+impl Tabular for ResourceAction {
+    fn headers() -> Vec<&'static str> {
+        vec!["RESOURCE", "ID", "ACTION", "STATE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        let state = match (&self.state, self.converged) {
+            (Some(state), Some(false)) => format!("{} (not converged)", state),
+            (Some(state), _) => state.clone(),
+            (None, _) => String::new(),
+        };
+        vec![
+            self.resource.to_owned(),
+            self.id.clone(),
+            self.action.to_owned(),
+            state,
+        ]
+    }
+}
+
+fn action(resource: &'static str, id: &str, action: &'static str) -> ResourceAction {
+    ResourceAction {
+        resource,
+        id: id.to_owned(),
+        action,
+        state: None,
+        converged: None,
+    }
+}
+
+// Attaches the observed states to the actions on the same resources.
+fn verify(actions: &mut [ResourceAction], states: &[ResourceState]) {
+    for action in actions {
+        if let Some(state) = states.iter().find(|state| state.id == action.id) {
+            action.state = Some(state.state.clone());
+            action.converged = Some(state.converged);
+        }
+    }
+}
+
+// Marks resources which were listed but not acted on.
+fn found<'a>(
+    actions: &mut Vec<ResourceAction>,
+    resource: &'static str,
+    ids: impl IntoIterator<Item = &'a str>,
+) {
+    for id in ids {
+        if !actions.iter().any(|a| a.resource == resource && a.id == id) {
+            actions.push(action(resource, id, "found"));
+        }
+    }
+}
+
+// Teardown steps of a cluster, each starts once the steps it depends on are done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    EnableMaintenance,
+    DrainTargets,
+    ScaleDownServices,
+    ScaleDownAsgs,
+    TerminateInstances,
+    StopInstances,
+    DeleteNatGateways,
+    ReleaseAddresses,
+    StopDbInstances,
+    StopDbClusters,
+    HibernateReplicationGroups,
+    DeleteReplicationGroups,
+    DeleteServerlessCaches,
+    DeleteCacheClusters,
+    DeleteServices,
+    DeleteDbInstances,
+    DeleteDbClusters,
+    DeleteLoadBalancers,
+    DeleteTargetGroups,
+    RecreateNatGateways,
+    StartInstances,
+    ScaleUpAsgs,
+    ScaleUpServices,
+    DisableMaintenance,
+}
+
+impl Step {
+    fn depends_on(self) -> &'static [Step] {
+        match self {
+            Step::EnableMaintenance | Step::RecreateNatGateways => &[],
+            // Instances register with the cluster through the NAT gateways.
+            Step::ScaleUpAsgs | Step::StartInstances => &[Step::RecreateNatGateways],
+            // Tasks need the container instances of the groups and the started instances.
+            Step::ScaleUpServices => &[Step::ScaleUpAsgs, Step::StartInstances],
+            // New requests are answered by the maintenance rule while targets drain.
+            Step::DrainTargets => &[Step::EnableMaintenance],
+            // Targets drain before their tasks stop.
+            Step::ScaleDownServices => &[Step::DrainTargets],
+            // Tasks stop before anything they use goes away.
+            Step::ScaleDownAsgs
+            | Step::StopDbInstances
+            | Step::StopDbClusters
+            | Step::HibernateReplicationGroups
+            | Step::DeleteReplicationGroups
+            | Step::DeleteServerlessCaches
+            | Step::DeleteCacheClusters
+            | Step::DeleteServices
+            | Step::DeleteDbInstances
+            | Step::DeleteDbClusters => &[Step::ScaleDownServices],
+            Step::TerminateInstances | Step::StopInstances => &[Step::ScaleDownAsgs],
+            // Instances route through the NAT gateways until they are gone or stopped.
+            Step::DeleteNatGateways => &[Step::TerminateInstances, Step::StopInstances],
+            // An Elastic IP is associated until its NAT gateway is gone.
+            Step::ReleaseAddresses => &[Step::DeleteNatGateways],
+            // Services deregister their targets while they are deleted.
+            Step::DeleteLoadBalancers => &[Step::DeleteServices],
+            // Target groups are in use until their load balancer is gone.
+            Step::DeleteTargetGroups => &[Step::DeleteLoadBalancers],
+            // Traffic returns once the tasks are running again.
+            Step::DisableMaintenance => &[Step::ScaleUpServices],
+        }
+    }
+
+    fn for_args(args: &ScaleInEcsArgs) -> Vec<Step> {
+        if args.scaleup {
+            return vec![
+                Step::RecreateNatGateways,
+                Step::StartInstances,
+                Step::ScaleUpAsgs,
+                Step::ScaleUpServices,
+                Step::DisableMaintenance,
+            ];
+        }
+
+        let mut steps = Vec::new();
+        if args.migration || args.scaledown || args.delete {
+            if args.maintenance {
+                steps.push(Step::EnableMaintenance);
+            }
+            if args.drain {
+                steps.push(Step::DrainTargets);
+            }
+        }
+        if args.migration {
+            steps.push(Step::ScaleDownServices);
+            return steps;
+        }
+        if args.scaledown || args.delete {
+            steps.extend([
+                Step::ScaleDownServices,
+                Step::ScaleDownAsgs,
+                Step::TerminateInstances,
+                Step::StopInstances,
+                Step::DeleteNatGateways,
+            ]);
+            if args.release_eips {
+                steps.push(Step::ReleaseAddresses);
+            }
+        }
+        if args.scaledown {
+            steps.extend([Step::StopDbInstances, Step::StopDbClusters]);
+        }
+        if args.hibernate_caches {
+            steps.push(Step::HibernateReplicationGroups);
+        }
+        if args.delete {
+            steps.extend([
+                Step::DeleteReplicationGroups,
+                Step::DeleteServerlessCaches,
+                Step::DeleteCacheClusters,
+                Step::DeleteServices,
+                Step::DeleteDbInstances,
+                Step::DeleteDbClusters,
+                Step::DeleteLoadBalancers,
+                Step::DeleteTargetGroups,
+            ]);
+        }
+        steps
+    }
+}
+
+// Groups the steps into stages which run one after another, the steps of a stage run
+// concurrently. Dependencies which are not part of the run are ignored.
+fn stages(steps: &[Step]) -> Vec<Vec<Step>> {
+    let mut done: Vec<Step> = Vec::new();
+    let mut remaining = steps.to_vec();
+    let mut stages = Vec::new();
+
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<Step>, Vec<Step>) = remaining.iter().partition(|step| {
+            step.depends_on()
+                .iter()
+                .all(|dependency| done.contains(dependency) || !steps.contains(dependency))
+        });
+        // Step::depends_on has no cycles, so every round makes progress.
+        assert!(!ready.is_empty(), "Cyclic teardown steps: {:?}", blocked);
+        done.extend(&ready);
+        stages.push(ready);
+        remaining = blocked;
+    }
+    stages
+}
+
+// Runs the operation for every item, at most `parallel` at once, and fails once all finished.
+async fn for_each<'a, T, F, Fut>(
+    items: &'a [T],
+    parallel: usize,
+    operation: F,
+) -> AppResult<Vec<ResourceAction>>
+where
+    F: Fn(&'a T) -> Fut,
+    Fut: Future<Output = AppResult<ResourceAction>>,
+{
+    let results: Vec<AppResult<ResourceAction>> = stream::iter(items)
+        .map(operation)
+        .buffered(parallel.max(1))
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
+
+#[derive(Debug, Default)]
+struct Resources {
+    services: Vec<EcsService>,
+    scalable_targets: Vec<ScalableTarget>,
+    asgs: Vec<AutoScalingGroup>,
+    load_balancers: Vec<LoadBalancer>,
+    target_groups: Vec<TargetGroup>,
+    nat_gateways: Vec<NatGateway>,
+    nat_gateway_records: Vec<NatGatewayRecord>,
+    ec2_instances: Vec<Ec2Instance>,
+    db_instances: Vec<DbInstance>,
+    db_clusters: Vec<DbCluster>,
+    replication_groups: Vec<ReplicationGroup>,
+    serverless_caches: Vec<ServerlessCache>,
+    cache_clusters: Vec<CacheCluster>,
+}
+
+struct Teardown<'a> {
+    target: &'a Target,
+    cluster: &'a str,
+    args: &'a ScaleInEcsArgs,
+    audit: &'a Audit,
+    steps: Vec<Step>,
+    resources: Resources,
+    ecs: EcsClient,
+    autoscaling: AutoScalingClient,
+    appautoscaling: AppAutoScalingClient,
+    ec2: Ec2Client,
+    elbv2: Elbv2Client,
+    rds: RdsClient,
+    elasticache: ElasticacheClient,
+}
+
+impl Teardown<'_> {
+    fn has_dependents(&self, step: Step) -> bool {
+        self.steps
+            .iter()
+            .any(|other| other.depends_on().contains(&step))
+    }
+
+    fn progress(&self, message: &str) {
+        eprintln!("[{}] {}", self.target, message);
+    }
+
+    // Target groups the services register their tasks in.
+    fn service_target_groups(&self) -> Vec<String> {
+        let mut arns: Vec<String> = Vec::new();
+        for arn in self
+            .resources
+            .services
+            .iter()
+            .flat_map(|s| &s.target_groups)
+        {
+            if !arns.contains(arn) {
+                arns.push(arn.clone());
+            }
+        }
+        arns
+    }
+
+    // Instances with the `ScaleIn` policy, those tagged `skip` are left alone.
+    fn instance_ids(&self, policy: InstancePolicy) -> Vec<String> {
+        self.resources
+            .ec2_instances
+            .iter()
+            .filter(|instance| instance.policy == policy)
+            .map(|instance| instance.id.clone())
+            .collect()
+    }
+
+    // The scalable target of the service, if Application Auto Scaling manages it.
+    fn scalable_target(&self, service: &EcsService) -> Option<&ScalableTarget> {
+        let resource_id = appautoscaling::service_resource_id(self.cluster, &service.name);
+        self.resources
+            .scalable_targets
+            .iter()
+            .find(|scalable_target| scalable_target.resource_id == resource_id)
+    }
+
+    // A step waits for its changes to take effect when a later step relies on them, or
+    // always with --wait. Dependent steps do not start after a step which did not converge.
+    async fn run(&self, step: Step) -> AppResult<Vec<ResourceAction>> {
+        let mut actions = self.apply(step).await?;
+        if actions.is_empty() || !(self.args.wait || self.has_dependents(step)) {
+            return Ok(actions);
+        }
+
+        let Some(states) = self.wait(step).await? else {
+            return Ok(actions);
+        };
+        verify(&mut actions, &states);
+
+        let pending: Vec<&str> = states
+            .iter()
+            .filter(|state| !state.converged)
+            .map(|state| state.id.as_str())
+            .collect();
+        if !pending.is_empty() && self.has_dependents(step) {
+            return Err(format!(
+                "[{}] {:?} did not converge within {} s ({}), dependent steps were not run",
+                self.target,
+                step,
+                self.args.wait_timeout,
+                pending.join(", ")
+            )
+            .into());
+        }
+        Ok(actions)
+    }
+
+    async fn wait(&self, step: Step) -> AppResult<Option<Vec<ResourceState>>> {
+        let timeout = Duration::from_secs(self.args.wait_timeout);
+        let resources = &self.resources;
+        let service_arns =
+            || -> Vec<String> { resources.services.iter().map(|s| s.arn.clone()).collect() };
+
+        let states = match step {
+            // Rules take effect once CreateRule or DeleteRule returned.
+            Step::EnableMaintenance | Step::DisableMaintenance => return Ok(None),
+            Step::DrainTargets => {
+                elbv2::wait_for_targets_drained(&self.elbv2, &self.service_target_groups(), timeout)
+                    .await?
+            }
+            Step::ScaleUpServices => {
+                ecs::wait_for_services_scaled_up(&self.ecs, self.cluster, &service_arns(), timeout)
+                    .await?
+            }
+            Step::ScaleDownServices => {
+                ecs::wait_for_services_scaled_down(
+                    &self.ecs,
+                    self.cluster,
+                    &service_arns(),
+                    timeout,
+                )
+                .await?
+            }
+            // The instances of the groups are waited on by TerminateInstances, on scale up
+            // the services place their tasks once the instances registered.
+            Step::ScaleDownAsgs | Step::ScaleUpAsgs => return Ok(None),
+            Step::TerminateInstances => {
+                let ids = self.instance_ids(InstancePolicy::Terminate);
+                ec2::wait_for_instances_terminated(&self.ec2, &ids, timeout).await?
+            }
+            Step::StopInstances => {
+                let ids = self.instance_ids(InstancePolicy::Stop);
+                ec2::wait_for_instances_stopped(&self.ec2, &ids, timeout).await?
+            }
+            Step::StartInstances => {
+                let ids: Vec<String> = resources
+                    .ec2_instances
+                    .iter()
+                    .map(|i| i.id.clone())
+                    .collect();
+                ec2::wait_for_instances_running(&self.ec2, &ids, timeout).await?
+            }
+            Step::DeleteNatGateways => {
+                let ids: Vec<String> = resources
+                    .nat_gateways
+                    .iter()
+                    .map(|n| n.id.clone())
+                    .collect();
+                ec2::wait_for_nat_gateways_deleted(&self.ec2, &ids, timeout).await?
+            }
+            Step::StopDbInstances => {
+                let ids: Vec<String> = resources
+                    .db_instances
+                    .iter()
+                    .map(|d| d.id.clone())
+                    .collect();
+                rds::wait_for_db_instances_stopped(&self.rds, &ids, timeout).await?
+            }
+            Step::StopDbClusters => {
+                let ids: Vec<String> = resources.db_clusters.iter().map(|c| c.id.clone()).collect();
+                rds::wait_for_db_clusters_stopped(&self.rds, &ids, timeout).await?
+            }
+            Step::HibernateReplicationGroups | Step::DeleteReplicationGroups => {
+                let ids: Vec<String> = resources
+                    .replication_groups
+                    .iter()
+                    .map(|r| r.id.clone())
+                    .collect();
+                elasticache::wait_for_replication_groups_deleted(&self.elasticache, &ids, timeout)
+                    .await?
+            }
+            Step::DeleteServerlessCaches => {
+                let names: Vec<String> = resources
+                    .serverless_caches
+                    .iter()
+                    .map(|c| c.name.clone())
+                    .collect();
+                elasticache::wait_for_serverless_caches_deleted(&self.elasticache, &names, timeout)
+                    .await?
+            }
+            Step::DeleteCacheClusters => {
+                let ids: Vec<String> = resources
+                    .cache_clusters
+                    .iter()
+                    .map(|c| c.id.clone())
+                    .collect();
+                elasticache::wait_for_cache_clusters_deleted(&self.elasticache, &ids, timeout)
+                    .await?
+            }
+            Step::DeleteServices => {
+                ecs::wait_for_services_deleted(&self.ecs, self.cluster, &service_arns(), timeout)
+                    .await?
+            }
+            Step::DeleteDbInstances => {
+                let ids: Vec<String> = resources
+                    .db_instances
+                    .iter()
+                    .map(|d| d.id.clone())
+                    .collect();
+                rds::wait_for_db_instances_deleted(&self.rds, &ids, timeout).await?
+            }
+            Step::DeleteDbClusters => {
+                let ids: Vec<String> = resources.db_clusters.iter().map(|c| c.id.clone()).collect();
+                rds::wait_for_db_clusters_deleted(&self.rds, &ids, timeout).await?
+            }
+            Step::DeleteLoadBalancers => {
+                let arns: Vec<String> = resources
+                    .load_balancers
+                    .iter()
+                    .map(|l| l.arn.clone())
+                    .collect();
+                elbv2::wait_for_load_balancers_deleted(&self.elbv2, &arns, timeout).await?
+            }
+            // A target group is gone once DeleteTargetGroup returned, an Elastic IP once
+            // ReleaseAddress returned. Recreated NAT gateways are waited on one by one.
+            Step::DeleteTargetGroups | Step::ReleaseAddresses | Step::RecreateNatGateways => {
+                return Ok(None);
+            }
+        };
+        Ok(Some(states))
+    }
+
+    // Members go first, the cluster cannot be deleted while it has instances.
+    async fn delete_db_cluster(&self, db_cluster: &DbCluster) -> AppResult<()> {
+        rds::disable_cluster_deletion_protection(&self.rds, self.audit, &db_cluster.id).await?;
+        for member in &db_cluster.members {
+            rds::delete_db_cluster_member(&self.rds, self.audit, member).await?;
+        }
+
+        let timeout = Duration::from_secs(self.args.wait_timeout);
+        let states =
+            rds::wait_for_db_instances_deleted(&self.rds, &db_cluster.members, timeout).await?;
+        if states.iter().any(|state| !state.converged) {
+            return Err(format!(
+                "Members of {} were not deleted within {} s",
+                db_cluster.id, self.args.wait_timeout
+            )
+            .into());
+        }
+
+        if self.args.skip_final_rds_snapshot {
+            rds::delete_db_cluster_skip_final_snapshot(&self.rds, self.audit, &db_cluster.id).await
+        } else {
+            rds::delete_db_cluster_with_final_snapshot(&self.rds, self.audit, &db_cluster.id).await
+        }
+    }
+
+    // Listeners go first, so rules no longer hold on to the target groups.
+    async fn delete_load_balancer(&self, load_balancer: &LoadBalancer) -> AppResult<()> {
+        elbv2::disable_deletion_protection(&self.elbv2, self.audit, &load_balancer.arn).await?;
+        for listener in elbv2::list_listeners(&self.elbv2, &load_balancer.arn).await? {
+            elbv2::delete_listener(&self.elbv2, self.audit, &listener.arn).await?;
+        }
+        elbv2::delete_load_balancer(&self.elbv2, self.audit, &load_balancer.arn).await
+    }
+
+    // The routes are repointed once the new gateway is available, the record is removed
+    // last, so a failed run can be repeated.
+    async fn recreate_nat_gateway(&self, record: &NatGatewayRecord) -> AppResult<ResourceAction> {
+        let gateway_id = ec2::create_nat_gateway(&self.ec2, self.audit, record).await?;
+        let timeout = Duration::from_secs(self.args.wait_timeout);
+        let states =
+            ec2::wait_for_nat_gateways_available(&self.ec2, &[gateway_id.clone()], timeout).await?;
+        if states.iter().any(|state| !state.converged) {
+            return Err(format!(
+                "NAT gateway {} in {} was not available within {} s",
+                gateway_id, record.subnet_id, self.args.wait_timeout
+            )
+            .into());
+        }
+
+        for route_table_id in &record.route_table_ids {
+            ec2::point_default_route(&self.ec2, self.audit, route_table_id, &gateway_id).await?;
+        }
+        ec2::clear_nat_gateway_record(&self.ec2, self.audit, record).await?;
+
+        let mut recreated = action("nat-gateway", &gateway_id, "recreated");
+        verify(std::slice::from_mut(&mut recreated), &states);
+        Ok(recreated)
+    }
+
+    // Adds the maintenance rule to, or removes it from, every HTTP and HTTPS listener.
+    async fn maintenance(&self, load_balancer: &LoadBalancer, enable: bool) -> AppResult<bool> {
+        let mut changed = false;
+        for listener in elbv2::list_listeners(&self.elbv2, &load_balancer.arn).await? {
+            if !listener.has_rules() {
+                continue;
+            }
+            if enable {
+                elbv2::add_maintenance_rule(&self.elbv2, self.audit, &listener.arn).await?;
+                changed = true;
+                continue;
+            }
+            for rule_arn in elbv2::list_maintenance_rules(&self.elbv2, &listener.arn).await? {
+                elbv2::delete_rule(&self.elbv2, self.audit, &rule_arn).await?;
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    async fn apply(&self, step: Step) -> AppResult<Vec<ResourceAction>> {
+        let parallel = self.args.max_parallel;
+        let (audit, cluster) = (self.audit, self.cluster);
+        let resources = &self.resources;
+
+        match step {
+            Step::EnableMaintenance | Step::DisableMaintenance => {
+                if resources.load_balancers.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let enable = step == Step::EnableMaintenance;
+                self.progress(if enable {
+                    "Adding maintenance rules."
+                } else {
+                    "Removing maintenance rules."
+                });
+                for_each(
+                    &resources.load_balancers,
+                    parallel,
+                    |load_balancer| async move {
+                        let changed = self.maintenance(load_balancer, enable).await?;
+                        let done = match (changed, enable) {
+                            (false, _) => "found",
+                            (true, true) => "maintenance-on",
+                            (true, false) => "maintenance-off",
+                        };
+                        Ok(action("load-balancer", &load_balancer.name, done))
+                    },
+                )
+                .await
+            }
+            Step::DrainTargets => {
+                let target_groups = self.service_target_groups();
+                if target_groups.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Draining targets.");
+                for_each(&target_groups, parallel, |arn| async move {
+                    elbv2::deregister_targets(&self.elbv2, audit, arn).await?;
+                    Ok(action("target-group", elbv2::resource_name(arn), "drained"))
+                })
+                .await
+            }
+            Step::ScaleUpServices => {
+                if resources.services.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Scaling up ECS services.");
+                for_each(&resources.services, parallel, |service| async move {
+                    let Some(desired_count) = service.restore_count else {
+                        return Ok(action("ecs-service", &service.name, "found"));
+                    };
+                    ecs::scale_up_service(&self.ecs, audit, cluster, &service.arn, desired_count)
+                        .await?;
+                    // Scaling resumes once the desired count is back, with the recorded min.
+                    if let (Some(scalable_target), Some(min_capacity)) =
+                        (self.scalable_target(service), service.restore_min_capacity)
+                        && scalable_target.suspended
+                    {
+                        appautoscaling::resume_scaling(
+                            &self.appautoscaling,
+                            audit,
+                            &scalable_target.resource_id,
+                            min_capacity,
+                        )
+                        .await?;
+                    }
+                    Ok(action("ecs-service", &service.name, "scaled-up"))
+                })
+                .await
+            }
+            Step::ScaleDownServices => {
+                if resources.services.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Scaling down ECS services.");
+                for_each(&resources.services, parallel, |service| async move {
+                    // Application Auto Scaling would scale the service back up to its min
+                    // capacity. A target suspended by an earlier run keeps its recorded min.
+                    if let Some(scalable_target) = self.scalable_target(service)
+                        && !scalable_target.suspended
+                    {
+                        ecs::tag_min_capacity(
+                            &self.ecs,
+                            audit,
+                            &service.arn,
+                            scalable_target.min_capacity,
+                        )
+                        .await?;
+                        appautoscaling::suspend_scaling(
+                            &self.appautoscaling,
+                            audit,
+                            &scalable_target.resource_id,
+                        )
+                        .await?;
+                    }
+                    // --scaleup restores the desired count from the tag.
+                    ecs::tag_desired_count(&self.ecs, audit, &service.arn, service.desired_count)
+                        .await?;
+                    ecs::scale_down_service(&self.ecs, audit, cluster, &service.arn, 0).await?;
+                    Ok(action("ecs-service", &service.name, "scaled-down"))
+                })
+                .await
+            }
+            Step::ScaleDownAsgs => {
+                if resources.asgs.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Scaling down ASGs.");
+                for_each(&resources.asgs, parallel, |asg| async move {
+                    // Scaling policies, such as the one of a capacity provider with managed
+                    // scaling, and scheduled actions would raise the capacity again.
+                    autoscaling::suspend_scaling_processes(&self.autoscaling, audit, &asg.name)
+                        .await?;
+                    let capacity = Capacity {
+                        min_size: asg.min_size,
+                        max_size: asg.max_size,
+                        desired_capacity: asg.desired_capacity,
+                    };
+                    autoscaling::tag_capacity(&self.autoscaling, audit, &asg.name, capacity)
+                        .await?;
+                    autoscaling::scale_down_asg(&self.autoscaling, audit, &asg.name, 0).await?;
+                    Ok(action("asg", &asg.name, "scaled-down"))
+                })
+                .await
+            }
+            Step::ScaleUpAsgs => {
+                if resources.asgs.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Restoring ASGs.");
+                for_each(&resources.asgs, parallel, |asg| async move {
+                    let Some(capacity) = asg.restore else {
+                        return Ok(action("asg", &asg.name, "found"));
+                    };
+                    autoscaling::restore_asg(&self.autoscaling, audit, &asg.name, capacity).await?;
+                    autoscaling::resume_scaling_processes(&self.autoscaling, audit, &asg.name)
+                        .await?;
+                    Ok(action("asg", &asg.name, "scaled-up"))
+                })
+                .await
+            }
+            Step::TerminateInstances => {
+                let ids = self.instance_ids(InstancePolicy::Terminate);
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Terminating EC2 instances.");
+                for_each(&ids, parallel, |id| async move {
+                    ec2::terminate_ec2_instance(&self.ec2, audit, id).await?;
+                    Ok(action("ec2-instance", id, "terminated"))
+                })
+                .await
+            }
+            Step::StopInstances => {
+                let ids = self.instance_ids(InstancePolicy::Stop);
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Stopping EC2 instances.");
+                for_each(&ids, parallel, |id| async move {
+                    ec2::stop_ec2_instance(&self.ec2, audit, id).await?;
+                    Ok(action("ec2-instance", id, "stopped"))
+                })
+                .await
+            }
+            Step::StartInstances => {
+                if resources.ec2_instances.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Starting EC2 instances.");
+                for_each(&resources.ec2_instances, parallel, |instance| async move {
+                    ec2::start_ec2_instance(&self.ec2, audit, &instance.id).await?;
+                    Ok(action("ec2-instance", &instance.id, "started"))
+                })
+                .await
+            }
+            Step::DeleteNatGateways => {
+                if resources.nat_gateways.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting NAT gateways.");
+                for_each(
+                    &resources.nat_gateways,
+                    parallel,
+                    |nat_gateway| async move {
+                        // RecreateNatGateways of --scaleup brings the gateway back from the record.
+                        ec2::record_nat_gateway(&self.ec2, audit, nat_gateway).await?;
+                        ec2::delete_nat_gateway(&self.ec2, audit, &nat_gateway.id).await?;
+                        Ok(action("nat-gateway", &nat_gateway.id, "deleted"))
+                    },
+                )
+                .await
+            }
+            Step::ReleaseAddresses => {
+                let allocation_ids: Vec<String> = resources
+                    .nat_gateways
+                    .iter()
+                    .flat_map(|n| n.allocation_ids.clone())
+                    .collect();
+                if allocation_ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Releasing Elastic IPs.");
+                for_each(&allocation_ids, parallel, |allocation_id| async move {
+                    ec2::release_address(&self.ec2, audit, allocation_id).await?;
+                    Ok(action("elastic-ip", allocation_id, "released"))
+                })
+                .await
+            }
+            Step::RecreateNatGateways => {
+                if resources.nat_gateway_records.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Recreating NAT gateways.");
+                for_each(&resources.nat_gateway_records, parallel, |record| {
+                    self.recreate_nat_gateway(record)
+                })
+                .await
+            }
+            Step::StopDbInstances => {
+                if resources.db_instances.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Stopping RDS instances.");
+                for_each(
+                    &resources.db_instances,
+                    parallel,
+                    |db_instance| async move {
+                        rds::stop_db_instance(&self.rds, audit, &db_instance.id).await?;
+                        Ok(action("db-instance", &db_instance.id, "stopped"))
+                    },
+                )
+                .await
+            }
+            Step::StopDbClusters => {
+                if resources.db_clusters.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Stopping Aurora clusters.");
+                for_each(&resources.db_clusters, parallel, |db_cluster| async move {
+                    rds::stop_db_cluster(&self.rds, audit, &db_cluster.id).await?;
+                    Ok(action("db-cluster", &db_cluster.id, "stopped"))
+                })
+                .await
+            }
+            Step::HibernateReplicationGroups => {
+                if resources.replication_groups.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Hibernating elasticache replication groups.");
+                let timeout = Duration::from_secs(self.args.wait_timeout);
+                for_each(
+                    &resources.replication_groups,
+                    parallel,
+                    |group| async move {
+                        let hibernation =
+                            elasticache::describe_hibernation(&self.elasticache, &group.id).await?;
+                        elasticache::hibernate_replication_group(
+                            &self.elasticache,
+                            audit,
+                            &hibernation,
+                            timeout,
+                        )
+                        .await?;
+                        Ok(action("replication-group", &group.id, "hibernated"))
+                    },
+                )
+                .await
+            }
+            Step::DeleteReplicationGroups => {
+                if resources.replication_groups.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting elasticache replication groups.");
+                for_each(
+                    &resources.replication_groups,
+                    parallel,
+                    |group| async move {
+                        elasticache::delete_replication_group(
+                            &self.elasticache,
+                            audit,
+                            &group.id,
+                            self.args.final_cache_snapshot,
+                        )
+                        .await?;
+                        Ok(action("replication-group", &group.id, "deleted"))
+                    },
+                )
+                .await
+            }
+            Step::DeleteServerlessCaches => {
+                if resources.serverless_caches.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting elasticache serverless caches.");
+                for_each(&resources.serverless_caches, parallel, |cache| async move {
+                    elasticache::delete_serverless_cache(
+                        &self.elasticache,
+                        audit,
+                        cache,
+                        self.args.final_cache_snapshot,
+                    )
+                    .await?;
+                    Ok(action("serverless-cache", &cache.name, "deleted"))
+                })
+                .await
+            }
+            Step::DeleteCacheClusters => {
+                if resources.cache_clusters.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting elasticache cache clusters.");
+                for_each(
+                    &resources.cache_clusters,
+                    parallel,
+                    |cache_cluster| async move {
+                        elasticache::delete_cache_cluster(
+                            &self.elasticache,
+                            audit,
+                            cache_cluster,
+                            self.args.final_cache_snapshot,
+                        )
+                        .await?;
+                        Ok(action("cache-cluster", &cache_cluster.id, "deleted"))
+                    },
+                )
+                .await
+            }
+            Step::DeleteServices => {
+                if resources.services.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting ECS services.");
+                for_each(&resources.services, parallel, |service| async move {
+                    ecs::delete_service(&self.ecs, audit, cluster, &service.arn).await?;
+                    Ok(action("ecs-service", &service.name, "deleted"))
+                })
+                .await
+            }
+            Step::DeleteDbInstances => {
+                if resources.db_instances.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting RDS.");
+                for_each(
+                    &resources.db_instances,
+                    parallel,
+                    |db_instance| async move {
+                        rds::disable_deletion_protection(&self.rds, audit, &db_instance.id).await?;
+                        if self.args.skip_final_rds_snapshot {
+                            rds::delete_db_instance_skip_final_snapshot(
+                                &self.rds,
+                                audit,
+                                &db_instance.id,
+                            )
+                            .await?;
+                        } else {
+                            // The snapshot keeps the configuration for restore-rds.
+                            let restorable =
+                                rds::describe_restorable_instance(&self.rds, &db_instance.id)
+                                    .await?;
+                            rds::delete_db_instance_for_restore(
+                                &self.rds,
+                                audit,
+                                &restorable,
+                                Duration::from_secs(self.args.wait_timeout),
+                            )
+                            .await?;
+                        }
+                        Ok(action("db-instance", &db_instance.id, "deleted"))
+                    },
+                )
+                .await
+            }
+            Step::DeleteDbClusters => {
+                if resources.db_clusters.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting Aurora clusters.");
+                for_each(&resources.db_clusters, parallel, |db_cluster| async move {
+                    self.delete_db_cluster(db_cluster).await?;
+                    Ok(action("db-cluster", &db_cluster.id, "deleted"))
+                })
+                .await
+            }
+            Step::DeleteLoadBalancers => {
+                if resources.load_balancers.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting load balancers.");
+                for_each(
+                    &resources.load_balancers,
+                    parallel,
+                    |load_balancer| async move {
+                        self.delete_load_balancer(load_balancer).await?;
+                        Ok(action("load-balancer", &load_balancer.name, "deleted"))
+                    },
+                )
+                .await
+            }
+            Step::DeleteTargetGroups => {
+                if resources.target_groups.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.progress("Deleting target groups.");
+                for_each(
+                    &resources.target_groups,
+                    parallel,
+                    |target_group| async move {
+                        // Groups shared with a load balancer of another cluster stay.
+                        if elbv2::target_group_in_use(&self.elbv2, &target_group.arn).await? {
+                            return Ok(action("target-group", &target_group.name, "in-use"));
+                        }
+                        elbv2::delete_target_group(&self.elbv2, audit, &target_group.arn).await?;
+                        Ok(action("target-group", &target_group.name, "deleted"))
+                    },
+                )
+                .await
+            }
+        }
+    }
+}
+
+// Adds the groups of the capacity providers of the cluster, whatever their name, which
+// match `keep` and are not known yet.
+async fn with_capacity_provider_asgs(
+    ecs_client: &EcsClient,
+    as_client: &AutoScalingClient,
+    cluster: &str,
+    mut asgs: Vec<AutoScalingGroup>,
+    keep: impl Fn(&AutoScalingGroup) -> bool,
+) -> AppResult<Vec<AutoScalingGroup>> {
+    let names: Vec<String> = ecs::capacity_provider_asgs(ecs_client, cluster)
+        .await?
+        .into_iter()
+        .filter(|name| !asgs.iter().any(|asg| &asg.name == name))
+        .collect();
+    if !names.is_empty() {
+        let provider_asgs = autoscaling::describe_asgs(as_client, &names).await?;
+        asgs.extend(provider_asgs.into_iter().filter(keep));
+    }
+    Ok(asgs)
+}
+
+async fn scale_in(
+    target: Target,
+    shared_config: SharedConfig,
+    audit: Audit,
+    args: &ScaleInEcsArgs,
+    cluster: &str,
+) -> AppResult<Vec<ResourceAction>> {
+    let ecs_client = initialize_client::<EcsClient>(&shared_config);
+    let as_client = initialize_client::<AutoScalingClient>(&shared_config);
+    let ec2_client = initialize_client::<Ec2Client>(&shared_config);
+    let elbv2_client = initialize_client::<Elbv2Client>(&shared_config);
+    let rds_client = initialize_client::<RdsClient>(&shared_config);
+    let elc_client = initialize_client::<ElasticacheClient>(&shared_config);
+    let aas_client = initialize_client::<AppAutoScalingClient>(&shared_config);
+
+    // Resources of different services are independent, so they are listed at once.
+    let resources = if args.scaleup {
+        // Services and groups scaled down by an earlier run, with what to restore tagged.
+        let (services, scalable_targets, asgs, load_balancers, nat_gateway_records, ec2_instances) =
+            futures::try_join!(
+                ecs::list_services(&ecs_client, cluster, -1),
+                appautoscaling::list_service_scalable_targets(&aas_client, cluster),
+                autoscaling::list_asgs(&as_client, cluster, -1),
+                elbv2::list_load_balancers(&elbv2_client, cluster),
+                ec2::list_nat_gateway_records(&ec2_client, cluster),
+                ec2::get_stopped_ec2_instances(&ec2_client, cluster),
+            )?;
+        let asgs = asgs
+            .into_iter()
+            .filter(|asg| asg.restore.is_some())
+            .collect();
+        Resources {
+            services: services
+                .into_iter()
+                .filter(|service| service.desired_count == 0 && service.restore_count.is_some())
+                .collect(),
+            scalable_targets,
+            asgs: with_capacity_provider_asgs(&ecs_client, &as_client, cluster, asgs, |asg| {
+                asg.restore.is_some()
+            })
+            .await?,
+            load_balancers,
+            nat_gateway_records,
+            ec2_instances,
+            ..Resources::default()
+        }
+    } else if args.migration {
+        let load_balancers = if args.maintenance {
+            elbv2::list_load_balancers(&elbv2_client, cluster).await?
+        } else {
+            Vec::new()
+        };
+        let (services, scalable_targets) = futures::try_join!(
+            ecs::list_services(&ecs_client, cluster, 0),
+            appautoscaling::list_service_scalable_targets(&aas_client, cluster),
+        )?;
+        Resources {
+            services,
+            scalable_targets,
+            load_balancers,
+            ..Resources::default()
+        }
+    } else {
+        let (
+            services,
+            scalable_targets,
+            asgs,
+            load_balancers,
+            nat_gateways,
+            ec2_instances,
+            db_instances,
+            db_clusters,
+            replication_groups,
+            serverless_caches,
+            cache_clusters,
+        ) = futures::try_join!(
+            ecs::list_services(&ecs_client, cluster, 0),
+            appautoscaling::list_service_scalable_targets(&aas_client, cluster),
+            autoscaling::list_asgs(&as_client, cluster, 0),
+            elbv2::list_load_balancers(&elbv2_client, cluster),
+            ec2::get_nat_gateways(&ec2_client, cluster),
+            ec2::get_ec2_instances(&ec2_client, cluster),
+            rds::list_db_instances(&rds_client, cluster),
+            rds::list_db_clusters(&rds_client, cluster),
+            elasticache::list_replication_groups(&elc_client, cluster),
+            elasticache::list_serverless_caches(&elc_client, cluster),
+            elasticache::list_cache_clusters(&elc_client, cluster),
+        )?;
+        // Target groups are listed by their load balancer, before it is deleted.
+        let mut target_groups: Vec<TargetGroup> = Vec::new();
+        for load_balancer in &load_balancers {
+            for target_group in elbv2::list_target_groups(&elbv2_client, &load_balancer.arn).await?
+            {
+                if !target_groups
+                    .iter()
+                    .any(|known| known.arn == target_group.arn)
+                {
+                    target_groups.push(target_group);
+                }
+            }
+        }
+        // Capacity providers may use groups which are not named after the cluster.
+        let asgs = with_capacity_provider_asgs(&ecs_client, &as_client, cluster, asgs, |asg| {
+            asg.desired_capacity != 0
+        })
+        .await?;
+        Resources {
+            services,
+            scalable_targets,
+            asgs,
+            load_balancers,
+            target_groups,
+            nat_gateways,
+            nat_gateway_records: Vec::new(),
+            ec2_instances,
+            db_instances,
+            db_clusters,
+            replication_groups,
+            serverless_caches,
+            cache_clusters,
+        }
+    };
+    info!("[{}] Resources: {:?}", target, resources);
+
+    let teardown = Teardown {
+        target: &target,
+        cluster,
+        args,
+        audit: &audit,
+        steps: Step::for_args(args),
+        resources,
+        ecs: ecs_client,
+        autoscaling: as_client,
+        appautoscaling: aas_client,
+        ec2: ec2_client,
+        elbv2: elbv2_client,
+        rds: rds_client,
+        elasticache: elc_client,
+    };
+
+    let mut actions = Vec::new();
+    for stage in stages(&teardown.steps) {
+        debug!("[{}] Running {:?}", target, stage);
+        let results = join_all(stage.into_iter().map(|step| teardown.run(step))).await;
+        for result in results {
+            actions.extend(result?);
+        }
+    }
+
+    let resources = &teardown.resources;
+    found(
+        &mut actions,
+        "ecs-service",
+        resources.services.iter().map(|s| s.name.as_str()),
+    );
+    found(
+        &mut actions,
+        "asg",
+        resources.asgs.iter().map(|a| a.name.as_str()),
+    );
+    found(
+        &mut actions,
+        "ec2-instance",
+        resources.ec2_instances.iter().map(|i| i.id.as_str()),
+    );
+    found(
+        &mut actions,
+        "nat-gateway",
+        resources.nat_gateways.iter().map(|n| n.id.as_str()),
+    );
+    found(
+        &mut actions,
+        "db-instance",
+        resources.db_instances.iter().map(|d| d.id.as_str()),
+    );
+    found(
+        &mut actions,
+        "db-cluster",
+        resources.db_clusters.iter().map(|c| c.id.as_str()),
+    );
+    found(
+        &mut actions,
+        "replication-group",
+        resources.replication_groups.iter().map(|r| r.id.as_str()),
+    );
+    found(
+        &mut actions,
+        "serverless-cache",
+        resources.serverless_caches.iter().map(|c| c.name.as_str()),
+    );
+    found(
+        &mut actions,
+        "cache-cluster",
+        resources.cache_clusters.iter().map(|c| c.id.as_str()),
+    );
+    found(
+        &mut actions,
+        "load-balancer",
+        resources.load_balancers.iter().map(|l| l.name.as_str()),
+    );
+    found(
+        &mut actions,
+        "target-group",
+        resources.target_groups.iter().map(|t| t.name.as_str()),
+    );
+
+    debug!("Cluster: {} Target: {}.", cluster, target);
+
+    Ok(actions)
+}
+
+pub async fn run(global: &GlobalArgs, args: &ScaleInEcsArgs) -> AppResult<()> {
+    let cluster = args
+        .cluster
+        .as_deref()
+        .ok_or("--cluster is required, directly or through --env")?;
+
+    if args.delete && args.skip_final_rds_snapshot {
+        // Show confirmation prompt
+        eprint!(
+            "Are you sure you want to DROP all databases without a final snapshot?\n(Type YES to confirm): "
+        );
+        std::io::stderr().flush()?;
+
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        if input.trim() != "YES" {
+            eprintln!("Aborted. Databases were not dropped.");
+            return Ok(());
+        }
+        eprintln!("Confirmation received. Proceeding with database drop...");
+    }
+
+    let fanout = global.fanout().await?;
+    let lock = if args.delete || args.scaledown || args.migration || args.scaleup {
+        global.lock("scale-in-ecs", &fanout).await?
+    } else {
+        None
+    };
+
+    let results = fanout
+        .run(|target, shared_config| {
+            let audit = global.audit(&target, &shared_config);
+            scale_in(target, shared_config, audit, args, cluster)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let actions = with_targets(results?);
+    output::print(global.output, &actions)?;
+
+    let pending: Vec<String> = actions
+        .iter()
+        .filter(|action| action.item.converged == Some(false))
+        .map(|action| format!("{} {}", action.item.resource, action.item.id))
+        .collect();
+    if args.wait {
+        let verified = actions
+            .iter()
+            .filter(|action| action.item.converged.is_some())
+            .count();
+        eprintln!(
+            "Verified {} resources, {} did not converge.",
+            verified,
+            pending.len()
+        );
+    }
+    if !pending.is_empty() {
+        return Err(format!("Did not converge: {}", pending.join(", ")).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages() {
+        let steps = vec![
+            Step::ScaleDownServices,
+            Step::ScaleDownAsgs,
+            Step::TerminateInstances,
+            Step::StopInstances,
+            Step::DeleteNatGateways,
+            Step::DeleteReplicationGroups,
+            Step::DeleteServices,
+            Step::DeleteDbInstances,
+            Step::DeleteDbClusters,
+            Step::DeleteLoadBalancers,
+            Step::DeleteTargetGroups,
+        ];
+        assert_eq!(
+            stages(&steps),
+            vec![
+                vec![Step::ScaleDownServices],
+                vec![
+                    Step::ScaleDownAsgs,
+                    Step::DeleteReplicationGroups,
+                    Step::DeleteServices,
+                    Step::DeleteDbInstances,
+                    Step::DeleteDbClusters,
+                ],
+                vec![
+                    Step::TerminateInstances,
+                    Step::StopInstances,
+                    Step::DeleteLoadBalancers,
+                ],
+                vec![Step::DeleteNatGateways, Step::DeleteTargetGroups],
+            ]
+        );
+
+        assert_eq!(
+            stages(&[
+                Step::EnableMaintenance,
+                Step::DrainTargets,
+                Step::ScaleDownServices,
+                Step::ScaleDownAsgs,
+            ]),
+            vec![
+                vec![Step::EnableMaintenance],
+                vec![Step::DrainTargets],
+                vec![Step::ScaleDownServices],
+                vec![Step::ScaleDownAsgs],
+            ]
+        );
+
+        assert_eq!(
+            stages(&[
+                Step::RecreateNatGateways,
+                Step::StartInstances,
+                Step::ScaleUpAsgs,
+                Step::ScaleUpServices,
+                Step::DisableMaintenance,
+            ]),
+            vec![
+                vec![Step::RecreateNatGateways],
+                vec![Step::StartInstances, Step::ScaleUpAsgs],
+                vec![Step::ScaleUpServices],
+                vec![Step::DisableMaintenance],
+            ]
+        );
+
+        // Dependencies outside of the run do not hold steps back.
+        assert_eq!(
+            stages(&[Step::DeleteNatGateways, Step::StopDbInstances]),
+            vec![vec![Step::DeleteNatGateways, Step::StopDbInstances]]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    AppResult,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::initialize_client,
+    fanout::report_errors,
+    output::{self, Tabular, TargetRecord},
+    sesv2,
+};
+use aws_sdk_sesv2::Client as Sesv2Client;
+
+use clap::Args;
+use log::debug;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+
+pub const ABOUT: &str = "Exports ses suppression list";
+
+#[derive(Args, Debug, Clone)]
+pub struct SesSuppressionListArgs {
+    #[clap(
+        short = 'o',
+        long,
+        default_value = "suppressed_emails.csv",
+        help = "CSV file the addresses are written to"
+    )]
+    pub file: String,
+
+    #[clap(short, long, default_value = None)]
+    pub last: Option<u32>,
+
+    #[clap(short, long, help = "Include suppression date")]
+    pub full: bool,
+}
+
+impl EnvironmentDefaults for SesSuppressionListArgs {}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SuppressionExport {
+    pub file: String,
+    pub addresses: usize,
+}
+
+impl Tabular for SuppressionExport {
+    fn headers() -> Vec<&'static str> {
+        vec!["FILE", "ADDRESSES"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.file.clone(), self.addresses.to_string()]
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &SesSuppressionListArgs) -> AppResult<()> {
+    let fanout = global.fanout().await?;
+    let per_target_output = fanout.targets().len() > 1;
+
+    let results = fanout
+        .run(|target, shared_config| async move {
+            let sesv2_client = initialize_client::<Sesv2Client>(&shared_config);
+            let r = sesv2::get_suppression_list(&sesv2_client, args.last).await?;
+            debug!("[{}] Result: {:?}", target, &r);
+            Ok(r)
+        })
+        .await;
+
+    let mut exports = Vec::new();
+    for (target, r) in report_errors(results)? {
+        let output = if per_target_output {
+            format!("{}-{}-{}", target.profile, target.region, &args.file)
+        } else {
+            args.file.clone()
+        };
+        let mut file = File::create(format!("./{}", output))?;
+
+        for destination in &r {
+            if args.full {
+                writeln!(
+                    file,
+                    "{},{},{}",
+                    destination.email, destination.reason, destination.last_update
+                )?;
+            } else {
+                writeln!(file, "{},{}", destination.email, destination.reason)?;
+            }
+        }
+        exports.push(TargetRecord::new(
+            &target,
+            SuppressionExport {
+                file: output,
+                addresses: r.len(),
+            },
+        ));
+    }
+
+    output::print(global.output, &exports)
+}
@@ -0,0 +1,168 @@
+use crate::{
+    AppResult,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{initialize_client, load_shared_config},
+    config::Environment,
+    ecs,
+    output::{self, Tabular, TargetRecord},
+};
+use aws_config::Region;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sdk_ecs::Client as EcsClient;
+use clap::Args;
+use log::info;
+use serde::Serialize;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+pub const ABOUT: &str = "Starts an SSM session on the instance running an ECS service";
+
+const DEFAULT_SERVICE: &str = "auth";
+const DEFAULT_CLUSTER: &str = "app";
+
+#[derive(Args, Debug, Clone)]
+pub struct SsmSessionArgs {
+    #[clap(short, long, help = "[default: auth]")]
+    pub service: Option<String>,
+
+    #[clap(short, long, help = "[default: app]")]
+    pub cluster: Option<String>,
+
+    #[clap(short, long)]
+    pub exec: Option<String>,
+
+    #[clap(short, long, default_value = None)]
+    pub instance: Option<String>,
+
+    #[clap(short = 'H', long)]
+    pub host: bool,
+}
+
+impl EnvironmentDefaults for SsmSessionArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.service.is_none() {
+            self.service = environment.service.clone();
+        }
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SessionTarget {
+    pub cluster: String,
+    pub service: String,
+    pub instance_id: String,
+}
+
+impl Tabular for SessionTarget {
+    fn headers() -> Vec<&'static str> {
+        vec!["CLUSTER", "SERVICE", "INSTANCE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.cluster.clone(),
+            self.service.clone(),
+            self.instance_id.clone(),
+        ]
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &SsmSessionArgs) -> AppResult<()> {
+    let service = args.service.as_deref().unwrap_or(DEFAULT_SERVICE);
+    let cluster = args.cluster.as_deref().unwrap_or(DEFAULT_CLUSTER);
+    let target = global
+        .targets
+        .matrix()
+        .into_iter()
+        .next()
+        .ok_or("No profile or region given")?;
+    let credentials_provider = target.credentials_provider(&global.credentials).await;
+    let shared_config = load_shared_config(
+        Region::new(target.region.clone()),
+        &credentials_provider,
+        &global.client,
+    )
+    .await?;
+
+    let command = if let Some(exec) = &args.exec {
+        format!(
+            "command=sudo docker exec -ti $(sudo docker ps -qf name={} | head -n1) /bin/bash -lc {}",
+            service, exec
+        )
+    } else if args.instance.is_some() || args.host {
+        "command=sudo su -".to_string()
+    } else {
+        format!(
+            "command=sudo docker exec -ti $(sudo docker ps -qf name={} | head -n1) /bin/bash",
+            service
+        )
+    };
+
+    let ecs_client = initialize_client::<EcsClient>(&shared_config);
+    let instance_id = if let Some(instance) = &args.instance {
+        instance.to_owned()
+    } else {
+        let service_arn = ecs::get_service_arn(&ecs_client, cluster, service).await?;
+        info!("Service ARN: {}", service_arn);
+
+        let task_arn = ecs::get_task_arn(&ecs_client, cluster, &service_arn).await?;
+        info!("Task ARN: {}", task_arn);
+
+        let task_instance_arn =
+            ecs::get_task_container_arn(&ecs_client, cluster, &task_arn).await?;
+        info!("Task Instance ARN: {:?}", task_instance_arn);
+
+        ecs::get_container_arn(&ecs_client, cluster, &task_instance_arn).await?
+    };
+    info!("Instance ID: {:?}", instance_id);
+
+    output::print(
+        global.output,
+        &[TargetRecord::new(
+            &target,
+            SessionTarget {
+                cluster: cluster.to_owned(),
+                service: service.to_owned(),
+                instance_id: instance_id.clone(),
+            },
+        )],
+    )?;
+
+    let mut session = Command::new("aws");
+    session
+        .arg("ssm")
+        .arg("start-session")
+        .arg("--region")
+        .arg(&target.region)
+        .arg("--target")
+        .arg(instance_id)
+        .arg("--document-name")
+        .arg("AWS-StartInteractiveCommand")
+        .arg("--parameters");
+
+    if let Some(endpoint_url) = &global.client.endpoint_url {
+        session.arg("--endpoint-url").arg(endpoint_url);
+    }
+
+    // The AWS CLI cannot assume our role or SSO session itself, hand it the resolved credentials.
+    if target.role_arn.is_some()
+        || global.credentials.role_arn.is_some()
+        || global.credentials.sso_start_url.is_some()
+    {
+        let credentials = credentials_provider.provide_credentials().await?;
+        session
+            .env("AWS_ACCESS_KEY_ID", credentials.access_key_id())
+            .env("AWS_SECRET_ACCESS_KEY", credentials.secret_access_key());
+        if let Some(token) = credentials.session_token() {
+            session.env("AWS_SESSION_TOKEN", token);
+        }
+    } else {
+        session.arg("--profile").arg(&target.profile);
+    }
+
+    let _session = session.arg(command).exec();
+    Ok(())
+}
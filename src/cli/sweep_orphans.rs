@@ -0,0 +1,163 @@
+use aws_sdk_ec2::Client as Ec2Client;
+
+use crate::{
+    AppResult,
+    audit::Audit,
+    cli::{EnvironmentDefaults, GlobalArgs},
+    client::{SharedConfig, initialize_client},
+    config::Environment,
+    fanout::{Target, report_errors},
+    output::{self, OutputFormat, TargetRecord, with_targets},
+    sweeper::{self, Orphan},
+};
+use chrono::{Duration, Utc};
+use clap::Args;
+use std::io::Write;
+
+pub const ABOUT: &str =
+    "Find and delete EBS volumes, ENIs, Elastic IPs, images and snapshots left after teardown";
+
+#[derive(Args, Debug, Clone)]
+pub struct SweepOrphansArgs {
+    #[clap(short, long, help = "Required, directly or through --env")]
+    pub cluster: Option<String>,
+
+    #[clap(
+        long,
+        default_value = "7",
+        value_name = "DAYS",
+        help = "Images and snapshots newer than this are not orphaned"
+    )]
+    pub min_age_days: i64,
+
+    #[clap(long, help = "Delete the orphaned resources after confirmation")]
+    pub delete: bool,
+
+    #[clap(
+        long,
+        requires = "delete",
+        help = "Delete without asking for confirmation"
+    )]
+    pub yes: bool,
+}
+
+impl EnvironmentDefaults for SweepOrphansArgs {
+    fn apply_environment(&mut self, environment: &Environment) {
+        if self.cluster.is_none() {
+            self.cluster = environment.cluster.clone();
+        }
+    }
+}
+
+async fn find(
+    shared_config: SharedConfig,
+    args: &SweepOrphansArgs,
+    cluster: &str,
+) -> AppResult<Vec<Orphan>> {
+    let client = initialize_client::<Ec2Client>(&shared_config);
+    let created_before = Utc::now() - Duration::days(args.min_age_days);
+    sweeper::list_orphans(&client, cluster, created_before).await
+}
+
+// A resource which cannot be deleted, e.g. a snapshot another image still uses, does not
+// keep the others of the target.
+async fn sweep(
+    target: Target,
+    shared_config: SharedConfig,
+    audit: Audit,
+    orphans: Vec<Orphan>,
+) -> AppResult<Vec<Orphan>> {
+    let client = initialize_client::<Ec2Client>(&shared_config);
+    let mut swept = Vec::with_capacity(orphans.len());
+    for mut orphan in orphans {
+        eprintln!("[{}] Deleting {}", target, orphan.id);
+        orphan.action = match sweeper::delete_orphan(&client, &audit, &orphan).await {
+            Ok(()) => "deleted",
+            Err(err) => {
+                eprintln!("[{}] Cannot delete {}: {}", target, orphan.id, err);
+                "failed"
+            }
+        };
+        swept.push(orphan);
+    }
+    Ok(swept)
+}
+
+fn confirm(orphans: &[TargetRecord<Orphan>], monthly_cost: f64) -> AppResult<bool> {
+    eprintln!("{}", output::render(OutputFormat::Table, orphans)?);
+    eprint!(
+        "Are you sure you want to DELETE {} resources, about {:.2} USD per month?\n(Type YES to confirm): ",
+        orphans.len(),
+        monthly_cost
+    );
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim() == "YES")
+}
+
+pub async fn run(global: &GlobalArgs, args: &SweepOrphansArgs) -> AppResult<()> {
+    let cluster = args
+        .cluster
+        .as_deref()
+        .ok_or("--cluster is required, directly or through --env")?;
+
+    let fanout = global.fanout().await?;
+    let results = fanout
+        .run(|_, shared_config| find(shared_config, args, cluster))
+        .await;
+    let found = report_errors(results)?;
+
+    let orphans = with_targets(found.clone());
+    let monthly_cost: f64 = orphans.iter().map(|orphan| orphan.item.monthly_cost).sum();
+    if !args.delete || orphans.is_empty() {
+        output::print(global.output, &orphans)?;
+        eprintln!(
+            "Found {} orphaned resources, about {:.2} USD per month.",
+            orphans.len(),
+            monthly_cost
+        );
+        return Ok(());
+    }
+
+    if !args.yes && !confirm(&orphans, monthly_cost)? {
+        eprintln!("Aborted. Nothing was deleted.");
+        return Ok(());
+    }
+
+    let lock = global.lock("sweep-orphans", &fanout).await?;
+    let results = fanout
+        .run(|target, shared_config| {
+            let audit = global.audit(&target, &shared_config);
+            let orphans = found
+                .iter()
+                .find(|(found_target, _)| *found_target == target)
+                .map(|(_, orphans)| orphans.clone())
+                .unwrap_or_default();
+            sweep(target, shared_config, audit, orphans)
+        })
+        .await;
+    let results = report_errors(results);
+    if let Some(lock) = lock {
+        lock.release(&results).await?;
+    }
+
+    let swept = with_targets(results?);
+    output::print(global.output, &swept)?;
+
+    let failed: Vec<&str> = swept
+        .iter()
+        .filter(|orphan| orphan.item.action == "failed")
+        .map(|orphan| orphan.item.id.as_str())
+        .collect();
+    eprintln!(
+        "Deleted {} orphaned resources, {} failed.",
+        swept.len() - failed.len(),
+        failed.len()
+    );
+    if !failed.is_empty() {
+        return Err(format!("Not deleted: {}", failed.join(", ")).into());
+    }
+    Ok(())
+}
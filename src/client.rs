@@ -1,70 +1,256 @@
-use aws_config::Region;
-use aws_config::default_provider::credentials::DefaultCredentialsChain;
-use aws_sdk_autoscaling::{Client as AutoScalingClient, Config as AutoScalingConfig};
-use aws_sdk_codecommit::{Client as CodeCommitClient, Config as CodeCommitConfig};
-use aws_sdk_codepipeline::{Client as CodepipelineClient, Config as CodepipelineConfig};
-use aws_sdk_ec2::{Client as Ec2Client, Config as Ec2Config};
-use aws_sdk_ecs::{Client as EcsClient, Config as EcsConfig};
-use aws_sdk_elasticache::{Client as ElasticacheClient, Config as ElasticacheConfig};
-use aws_sdk_elasticloadbalancingv2::{Client as Elbv2Client, Config as Elbv2Config};
-use aws_sdk_guardduty::{Client as GDClient, Config as GDConfig};
-use aws_sdk_rds::{Client as RdsClient, Config as RdsConfig};
-use aws_sdk_s3::{Client as S3Client, Config as S3Config};
-use aws_sdk_sesv2::{Client as Sesv2Client, Config as Sesv2Config};
+use crate::AppResult;
+use aws_config::retry::RetryConfig;
+use aws_config::timeout::TimeoutConfig;
+use aws_config::{BehaviorVersion, Region, SdkConfig};
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_sdk_applicationautoscaling::{
+    Client as AppAutoScalingClient, config::Builder as AppAutoScalingConfig,
+};
+use aws_sdk_autoscaling::{Client as AutoScalingClient, config::Builder as AutoScalingConfig};
+use aws_sdk_codecommit::{Client as CodeCommitClient, config::Builder as CodeCommitConfig};
+use aws_sdk_codepipeline::{Client as CodepipelineClient, config::Builder as CodepipelineConfig};
+use aws_sdk_dynamodb::{Client as DynamoDbClient, config::Builder as DynamoDbConfig};
+use aws_sdk_ec2::{Client as Ec2Client, config::Builder as Ec2Config};
+use aws_sdk_ecs::{Client as EcsClient, config::Builder as EcsConfig};
+use aws_sdk_elasticache::{Client as ElasticacheClient, config::Builder as ElasticacheConfig};
+use aws_sdk_elasticloadbalancingv2::{Client as Elbv2Client, config::Builder as Elbv2Config};
+use aws_sdk_guardduty::{Client as GDClient, config::Builder as GDConfig};
+use aws_sdk_rds::{Client as RdsClient, config::Builder as RdsConfig};
+use aws_sdk_s3::{Client as S3Client, config::Builder as S3Config};
+use aws_sdk_sesv2::{Client as Sesv2Client, config::Builder as Sesv2Config};
+use aws_sdk_sts::{Client as StsClient, config::Builder as StsConfig};
+use aws_smithy_http_client::proxy::ProxyConfig;
+use aws_smithy_http_client::{Builder as HttpClientBuilder, tls};
+use clap::{Args, ValueEnum};
+use log::debug;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryMode {
+    Standard,
+    Adaptive,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ClientArgs {
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_ENDPOINT_URL",
+        help = "Endpoint URL for every service, e.g. a LocalStack instance"
+    )]
+    pub endpoint_url: Option<String>,
+
+    #[clap(
+        long = "service-endpoint",
+        global = true,
+        value_name = "SERVICE=URL",
+        value_parser = parse_service_endpoint,
+        help = "Endpoint URL for a single service, e.g. s3=http://localhost:4566"
+    )]
+    pub service_endpoints: Vec<(String, String)>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_S3_FORCE_PATH_STYLE",
+        help = "Use path-style S3 addressing"
+    )]
+    pub force_path_style: bool,
+
+    #[clap(long, global = true, env = "AWS_MAX_ATTEMPTS")]
+    pub max_attempts: Option<u32>,
+
+    #[clap(long, global = true, env = "AWS_RETRY_MODE", value_enum)]
+    pub retry_mode: Option<RetryMode>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_TOOLKIT_CONNECT_TIMEOUT",
+        help = "Connect timeout in seconds"
+    )]
+    pub connect_timeout: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_TOOLKIT_OPERATION_TIMEOUT",
+        help = "Operation timeout in seconds, including retries"
+    )]
+    pub operation_timeout: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "HTTPS_PROXY",
+        help = "HTTP proxy for all AWS calls"
+    )]
+    pub proxy: Option<String>,
+}
+
+fn parse_service_endpoint(value: &str) -> Result<(String, String), String> {
+    let (service, url) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected SERVICE=URL, got '{}'", value))?;
+    if service.is_empty() || url.is_empty() {
+        return Err(format!("expected SERVICE=URL, got '{}'", value));
+    }
+    Ok((service.to_lowercase(), url.to_owned()))
+}
+
+// One SdkConfig shared by every client of a run, plus the settings that are per service.
+#[derive(Debug, Clone)]
+pub struct SharedConfig {
+    pub sdk_config: SdkConfig,
+    service_endpoints: HashMap<String, String>,
+    force_path_style: bool,
+}
+
+impl SharedConfig {
+    pub fn region(&self) -> Option<&Region> {
+        self.sdk_config.region()
+    }
+
+    fn service_endpoint(&self, service: &str) -> Option<&str> {
+        self.service_endpoints.get(service).map(String::as_str)
+    }
+}
+
+pub async fn load_shared_config(
+    region: Region,
+    credentials_provider: &SharedCredentialsProvider,
+    args: &ClientArgs,
+) -> AppResult<SharedConfig> {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest())
+        .region(region)
+        .credentials_provider(credentials_provider.clone());
+
+    if let Some(endpoint_url) = &args.endpoint_url {
+        loader = loader.endpoint_url(endpoint_url);
+    }
+
+    if args.max_attempts.is_some() || args.retry_mode.is_some() {
+        let retry_config = match args.retry_mode {
+            Some(RetryMode::Adaptive) => RetryConfig::adaptive(),
+            _ => RetryConfig::standard(),
+        };
+        let retry_config = match args.max_attempts {
+            Some(max_attempts) => retry_config.with_max_attempts(max_attempts),
+            None => retry_config,
+        };
+        loader = loader.retry_config(retry_config);
+    }
+
+    if args.connect_timeout.is_some() || args.operation_timeout.is_some() {
+        let mut timeout_config = TimeoutConfig::builder();
+        timeout_config
+            .set_connect_timeout(args.connect_timeout.map(Duration::from_secs))
+            .set_operation_timeout(args.operation_timeout.map(Duration::from_secs));
+        loader = loader.timeout_config(timeout_config.build());
+    }
+
+    if let Some(proxy) = &args.proxy {
+        let proxy_config = ProxyConfig::all(proxy)?;
+        let http_client = HttpClientBuilder::new()
+            .tls_provider(tls::Provider::Rustls(
+                tls::rustls_provider::CryptoMode::AwsLc,
+            ))
+            .proxy_config(proxy_config)
+            .build_https();
+        loader = loader.http_client(http_client);
+    }
+
+    let sdk_config = loader.load().await;
+    debug!("Loaded SDK config: {:?}", sdk_config);
+
+    Ok(SharedConfig {
+        sdk_config,
+        service_endpoints: args.service_endpoints.iter().cloned().collect(),
+        force_path_style: args.force_path_style,
+    })
+}
 
 // Define a generic trait for AWS clients
 pub trait AwsClientBuilder {
     type Client;
-    type Config;
+    type ConfigBuilder;
+
+    // Service name used to match `--service-endpoint` overrides.
+    const SERVICE: &'static str;
 
-    fn from_config(config: Self::Config) -> Self::Client;
-    fn build_config(region: Region, credentials_provider: DefaultCredentialsChain) -> Self::Config;
+    fn from_config(config: Self::ConfigBuilder) -> Self::Client;
+    fn build_config(config: &SharedConfig) -> Self::ConfigBuilder;
 }
 
 // Macro to implement AwsClientBuilder for multiple services
 macro_rules! impl_aws_client_builder {
-    ($client:ty, $config:ty) => {
+    ($client:ty, $config:ty, $service:literal) => {
+        impl_aws_client_builder!($client, $config, $service, |builder, _config| builder);
+    };
+    ($client:ty, $config:ty, $service:literal, |$builder:ident, $shared:ident| $customize:expr) => {
         impl AwsClientBuilder for $client {
             type Client = $client;
-            type Config = $config;
+            type ConfigBuilder = $config;
+
+            const SERVICE: &'static str = $service;
 
             fn from_config(config: $config) -> $client {
-                <$client>::from_conf(config)
+                <$client>::from_conf(config.build())
             }
 
-            fn build_config(
-                region: Region,
-                credentials_provider: DefaultCredentialsChain,
-            ) -> $config {
-                <$config>::builder()
-                    .credentials_provider(credentials_provider)
-                    .region(region)
-                    .build()
+            fn build_config(config: &SharedConfig) -> $config {
+                let mut $builder = <$config>::from(&config.sdk_config);
+                if let Some(endpoint_url) = config.service_endpoint(Self::SERVICE) {
+                    $builder = $builder.endpoint_url(endpoint_url);
+                }
+                let $shared = config;
+                $customize
             }
         }
     };
 }
 
 // Implement the trait for AWS SDK clients
-impl_aws_client_builder!(AutoScalingClient, AutoScalingConfig);
-impl_aws_client_builder!(Ec2Client, Ec2Config);
-impl_aws_client_builder!(EcsClient, EcsConfig);
-impl_aws_client_builder!(RdsClient, RdsConfig);
-impl_aws_client_builder!(Sesv2Client, Sesv2Config);
-impl_aws_client_builder!(CodeCommitClient, CodeCommitConfig);
-impl_aws_client_builder!(ElasticacheClient, ElasticacheConfig);
-impl_aws_client_builder!(Elbv2Client, Elbv2Config);
-impl_aws_client_builder!(CodepipelineClient, CodepipelineConfig);
-impl_aws_client_builder!(S3Client, S3Config);
-impl_aws_client_builder!(GDClient, GDConfig);
-
-// Generic initialization function
-pub async fn initialize_client<C: AwsClientBuilder>(region: Region, profile: &str) -> C::Client {
-    let credentials_provider = DefaultCredentialsChain::builder()
-        .profile_name(profile)
-        .build()
-        .await;
-
-    let config = C::build_config(region, credentials_provider);
-    C::from_config(config)
+impl_aws_client_builder!(AutoScalingClient, AutoScalingConfig, "autoscaling");
+impl_aws_client_builder!(
+    AppAutoScalingClient,
+    AppAutoScalingConfig,
+    "application-autoscaling"
+);
+impl_aws_client_builder!(Ec2Client, Ec2Config, "ec2");
+impl_aws_client_builder!(EcsClient, EcsConfig, "ecs");
+impl_aws_client_builder!(RdsClient, RdsConfig, "rds");
+impl_aws_client_builder!(Sesv2Client, Sesv2Config, "sesv2");
+impl_aws_client_builder!(CodeCommitClient, CodeCommitConfig, "codecommit");
+impl_aws_client_builder!(ElasticacheClient, ElasticacheConfig, "elasticache");
+impl_aws_client_builder!(Elbv2Client, Elbv2Config, "elbv2");
+impl_aws_client_builder!(CodepipelineClient, CodepipelineConfig, "codepipeline");
+impl_aws_client_builder!(S3Client, S3Config, "s3", |builder, config| builder
+    .force_path_style(config.force_path_style));
+impl_aws_client_builder!(GDClient, GDConfig, "guardduty");
+impl_aws_client_builder!(StsClient, StsConfig, "sts");
+impl_aws_client_builder!(DynamoDbClient, DynamoDbConfig, "dynamodb");
+
+// Generic initialization function, all clients built from one SharedConfig share its
+// credentials, HTTP client, retry and timeout settings
+pub fn initialize_client<C: AwsClientBuilder>(config: &SharedConfig) -> C::Client {
+    let builder = C::build_config(config);
+    C::from_config(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_service_endpoint;
+
+    #[test]
+    fn test_parse_service_endpoint() {
+        assert_eq!(
+            parse_service_endpoint("S3=http://localhost:4566"),
+            Ok(("s3".to_owned(), "http://localhost:4566".to_owned()))
+        );
+        assert!(parse_service_endpoint("s3").is_err());
+        assert!(parse_service_endpoint("=http://localhost:4566").is_err());
+    }
 }
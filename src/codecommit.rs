@@ -1,11 +1,29 @@
 use crate::AppResult;
+use crate::output::Tabular;
 use aws_sdk_codecommit::Client;
 use log::debug;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Repository {
+    pub name: String,
+    pub id: Option<String>,
+}
+
+impl Tabular for Repository {
+    fn headers() -> Vec<&'static str> {
+        vec!["REPOSITORY", "ID"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.name.clone(), self.id.clone().unwrap_or_default()]
+    }
+}
 
 async fn list_filtered_repositories_internal<F>(
     client: &Client,
     filter: F,
-) -> AppResult<Vec<String>>
+) -> AppResult<Vec<Repository>>
 where
     F: Fn(&str) -> bool + Send + Sync,
 {
@@ -21,7 +39,10 @@ where
                 .filter_map(|repo| {
                     let repo_name = repo.repository_name()?;
                     if filter(repo_name) {
-                        Some(repo_name.to_owned())
+                        Some(Repository {
+                            name: repo_name.to_owned(),
+                            id: repo.repository_id().map(ToOwned::to_owned),
+                        })
                     } else {
                         None
                     }
@@ -37,7 +58,7 @@ pub async fn list_repositories(
     client: &Client,
     include: &[String],
     exclude: &[String],
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<Repository>> {
     let include: Vec<_> = include.iter().map(|x| x.as_str()).collect();
     let exclude: Vec<_> = exclude.iter().map(|x| x.as_str()).collect();
 
@@ -53,7 +74,7 @@ pub async fn list_exact_repositories(
     client: &Client,
     include: &[String],
     exclude: &[String],
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<Repository>> {
     let include: Vec<_> = include.iter().map(|x| x.as_str()).collect();
     let exclude: Vec<_> = exclude.iter().map(|x| x.as_str()).collect();
 
@@ -1,13 +1,33 @@
 use crate::AppResult;
+use crate::audit::Audit;
+use crate::output::Tabular;
+use crate::retry::{RetryPolicy, retry};
 use aws_sdk_codepipeline::Client;
-use aws_sdk_codepipeline::error::SdkError;
 use aws_sdk_codepipeline::types::StageExecutionStatus::{Failed, InProgress, Succeeded};
 use aws_sdk_codepipeline::types::StageState;
 use log::debug;
+use serde::Serialize;
 use std::time::Duration;
 use tokio::time::sleep;
 
-async fn list_filtered_pipelines_internal<F>(client: &Client, filter: F) -> AppResult<Vec<String>>
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub name: String,
+    // Status of the latest stage executions, only known once the pipeline state was fetched.
+    pub status: Option<String>,
+}
+
+impl Tabular for Pipeline {
+    fn headers() -> Vec<&'static str> {
+        vec!["PIPELINE", "STATUS"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.name.clone(), self.status.clone().unwrap_or_default()]
+    }
+}
+
+async fn list_filtered_pipelines_internal<F>(client: &Client, filter: F) -> AppResult<Vec<Pipeline>>
 where
     F: Fn(&str) -> bool + Send + Sync,
 {
@@ -19,7 +39,10 @@ where
         pipelines.extend(output?.pipelines().iter().filter_map(|p| {
             let pipeline_name = p.name()?;
             if filter(pipeline_name) {
-                Some(pipeline_name.to_owned())
+                Some(Pipeline {
+                    name: pipeline_name.to_owned(),
+                    status: None,
+                })
             } else {
                 None
             }
@@ -34,7 +57,7 @@ pub async fn list_all_pipelines(
     client: &Client,
     include: &[String],
     exclude: &[String],
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<Pipeline>> {
     let include = include.to_vec();
     let exclude = exclude.to_vec();
 
@@ -50,7 +73,7 @@ pub async fn list_pipelines(
     client: &Client,
     include: &[String],
     exclude: &[String],
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<Pipeline>> {
     let input = list_all_pipelines(client, include, exclude).await?;
 
     let filter_failure = |x: &StageState| {
@@ -76,7 +99,7 @@ pub async fn list_failed_pipelines(
     client: &Client,
     include: &[String],
     exclude: &[String],
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<Pipeline>> {
     let input = list_all_pipelines(client, include, exclude).await?;
 
     let filter_failure = |x: &StageState| {
@@ -112,12 +135,25 @@ fn get_stage_status(
         .status
 }
 
+// Failed if any stage failed, otherwise the status of the last executed stage.
+fn get_pipeline_status(stage_states: &[StageState]) -> Option<String> {
+    let statuses: Vec<_> = stage_states
+        .iter()
+        .filter(|stage_state| stage_state.latest_execution.is_some())
+        .map(get_stage_status)
+        .collect();
+    if statuses.contains(&&Failed) {
+        return Some(Failed.as_str().to_owned());
+    }
+    statuses.last().map(|status| status.as_str().to_owned())
+}
+
 async fn list_state_pipelines_internal<Ff, Fp>(
     client: &Client,
-    input: &Vec<String>,
+    input: &[Pipeline],
     filter_failure: Ff,
     filter_progress: Fp,
-) -> AppResult<Vec<String>>
+) -> AppResult<Vec<Pipeline>>
 where
     Ff: Fn(&StageState) -> bool + Send + Sync,
     Fp: Fn(&StageState) -> bool + Send + Sync,
@@ -125,14 +161,20 @@ where
     let mut pipelines = Vec::new();
 
     for pipeline in input {
-        let state = client.get_pipeline_state().name(pipeline).send().await?;
-        if state
+        let state = client
+            .get_pipeline_state()
+            .name(&pipeline.name)
+            .send()
+            .await?;
+        if let Some(stage_states) = state
             .stage_states
             .filter(|stage_states| stage_states.iter().any(&filter_failure))
             .filter(|stage_states| stage_states.iter().all(&filter_progress))
-            .is_some()
         {
-            pipelines.push(pipeline.to_owned());
+            pipelines.push(Pipeline {
+                name: pipeline.name.to_owned(),
+                status: get_pipeline_status(&stage_states),
+            });
         }
     }
 
@@ -142,56 +184,154 @@ where
 
 pub async fn release_pipelines<'a, F, Fut>(
     client: &'a Client,
+    audit: &Audit,
     include: &'a [String],
     exclude: &'a [String],
     pipeline_names_getter: F,
-) -> AppResult<()>
+) -> AppResult<Vec<Pipeline>>
 where
     F: Fn(&'a Client, &'a [String], &'a [String]) -> Fut + Send + Sync,
-    Fut: std::future::Future<Output = AppResult<Vec<String>>>,
+    Fut: std::future::Future<Output = AppResult<Vec<Pipeline>>>,
 {
     let pipelines = pipeline_names_getter(client, include, exclude).await?;
-    for pipeline in pipelines {
-        println!("{}", &pipeline);
-        release_pipeline(client, &pipeline).await?;
+    for pipeline in &pipelines {
+        debug!("Releasing pipeline {}", pipeline.name);
+        release_pipeline(client, audit, &pipeline.name).await?;
     }
-    Ok(())
+    Ok(pipelines)
 }
 
-pub async fn release_pipeline(client: &Client, pipeline_name: &str) -> AppResult<()> {
-    let max_retries = 3;
-    let mut retries = 0;
+pub async fn release_pipeline(
+    client: &Client,
+    audit: &Audit,
+    pipeline_name: &str,
+) -> AppResult<()> {
+    let policy = RetryPolicy::default()
+        .with_max_attempts(4)
+        .with_backoff(Duration::from_secs(20), Duration::from_secs(60));
 
     sleep(Duration::from_secs(1)).await;
-    loop {
-        match client
-            .start_pipeline_execution()
-            .name(pipeline_name)
-            .send()
-            .await
-        {
-            Ok(_) => {
-                sleep(Duration::from_secs(1)).await;
-                return Ok(());
-            } // Success, exit the loop
-            Err(SdkError::ServiceError(service_error)) => {
-                if let Some(code) = service_error.err().meta().code()
-                    && code == "ThrottlingException"
-                    && retries < max_retries
-                {
-                    retries += 1;
-                    eprintln!(
-                        "ThrottlingException encountered. Retrying in {} seconds... (attempt {}/{})",
-                        25 * retries,
-                        retries,
-                        max_retries
-                    );
-                    sleep(Duration::from_secs(20 * retries)).await;
-                    continue;
-                }
-                return Err(SdkError::ServiceError(service_error).into());
-            }
-            Err(err) => return Err(err.into()),
+    audit
+        .record(
+            "codepipeline:StartPipelineExecution",
+            &[("name", pipeline_name)],
+            retry(&policy, "start_pipeline_execution", || {
+                client.start_pipeline_execution().name(pipeline_name).send()
+            }),
+        )
+        .await?;
+    sleep(Duration::from_secs(1)).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_codepipeline::operation::get_pipeline_state::GetPipelineStateOutput;
+    use aws_sdk_codepipeline::operation::list_pipelines::ListPipelinesOutput;
+    use aws_sdk_codepipeline::types::{PipelineSummary, StageExecution, StageExecutionStatus};
+    use aws_smithy_mocks::{Rule, RuleMode, mock, mock_client};
+
+    fn stage(name: &str, status: Option<StageExecutionStatus>) -> StageState {
+        let builder = StageState::builder().stage_name(name);
+        match status {
+            Some(status) => builder
+                .latest_execution(
+                    StageExecution::builder()
+                        .pipeline_execution_id("execution")
+                        .status(status)
+                        .build()
+                        .unwrap(),
+                )
+                .build(),
+            None => builder.build(),
         }
     }
+
+    fn page(names: &[&str], next_token: Option<&str>) -> ListPipelinesOutput {
+        ListPipelinesOutput::builder()
+            .set_pipelines(Some(
+                names
+                    .iter()
+                    .map(|name| PipelineSummary::builder().name(*name).build())
+                    .collect(),
+            ))
+            .set_next_token(next_token.map(ToOwned::to_owned))
+            .build()
+    }
+
+    // Two pages of pipelines and the stage states of the ones matching "app" but not "legacy".
+    fn rules() -> Vec<Rule> {
+        let state = |pipeline: &'static str, stages: Vec<StageState>| {
+            mock!(Client::get_pipeline_state)
+                .match_requests(move |req| req.name() == Some(pipeline))
+                .then_output(move || {
+                    GetPipelineStateOutput::builder()
+                        .pipeline_name(pipeline)
+                        .set_stage_states(Some(stages.clone()))
+                        .build()
+                })
+        };
+
+        vec![
+            mock!(Client::list_pipelines)
+                .match_requests(|req| req.next_token().is_none())
+                .then_output(|| page(&["app-api", "app-legacy"], Some("page-2"))),
+            mock!(Client::list_pipelines)
+                .match_requests(|req| req.next_token() == Some("page-2"))
+                .then_output(|| page(&["app-web", "app-worker", "other"], None)),
+            state(
+                "app-api",
+                vec![
+                    stage("Source", Some(StageExecutionStatus::Succeeded)),
+                    stage("Deploy", Some(StageExecutionStatus::Failed)),
+                ],
+            ),
+            state(
+                "app-web",
+                vec![
+                    stage("Source", Some(StageExecutionStatus::Succeeded)),
+                    stage("Deploy", Some(StageExecutionStatus::InProgress)),
+                ],
+            ),
+            state(
+                "app-worker",
+                vec![
+                    stage("Source", Some(StageExecutionStatus::Succeeded)),
+                    stage("Deploy", None),
+                ],
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_list_pipelines_by_stage_state() {
+        let include = vec!["app".to_owned()];
+        let exclude = vec!["legacy".to_owned()];
+
+        let rules = rules();
+        let client = mock_client!(aws_sdk_codepipeline, RuleMode::MatchAny, &rules);
+        let pipelines = list_pipelines(&client, &include, &exclude).await.unwrap();
+        assert_eq!(
+            pipelines,
+            vec![
+                Pipeline {
+                    name: "app-api".to_owned(),
+                    status: Some("Failed".to_owned()),
+                },
+                Pipeline {
+                    name: "app-worker".to_owned(),
+                    status: Some("Succeeded".to_owned()),
+                },
+            ]
+        );
+
+        let rules = rules();
+        let client = mock_client!(aws_sdk_codepipeline, RuleMode::MatchAny, &rules);
+        let pipelines = list_failed_pipelines(&client, &include, &exclude)
+            .await
+            .unwrap();
+        let names: Vec<_> = pipelines.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["app-api"]);
+    }
 }
@@ -0,0 +1,229 @@
+use crate::AppResult;
+use log::debug;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Project-local config file, looked up in the current directory and its parents.
+pub const PROJECT_CONFIG_FILE: &str = "aws-toolkit.toml";
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub default_env: Option<String>,
+
+    #[serde(default)]
+    pub env: HashMap<String, Environment>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Selector {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub include: Vec<String>,
+
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RepoSelector {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub base: Vec<String>,
+
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub include: Vec<String>,
+
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub exclude: Vec<String>,
+}
+
+// A named set of defaults, every field is optional and command line flags take precedence.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Environment {
+    #[serde(default, alias = "profile", deserialize_with = "one_or_many")]
+    pub profiles: Vec<String>,
+
+    #[serde(default, alias = "region", deserialize_with = "one_or_many")]
+    pub regions: Vec<String>,
+
+    pub role_arn: Option<String>,
+    pub cluster: Option<String>,
+    pub service: Option<String>,
+    pub lock_table: Option<String>,
+
+    #[serde(default)]
+    pub pipelines: Selector,
+
+    #[serde(default)]
+    pub buckets: Selector,
+
+    #[serde(default)]
+    pub repos: RepoSelector,
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+fn merge_vec(base: &mut Vec<String>, other: Vec<String>) {
+    if !other.is_empty() {
+        *base = other;
+    }
+}
+
+impl Selector {
+    fn merge(&mut self, other: Selector) {
+        merge_vec(&mut self.include, other.include);
+        merge_vec(&mut self.exclude, other.exclude);
+    }
+}
+
+impl RepoSelector {
+    fn merge(&mut self, other: RepoSelector) {
+        merge_vec(&mut self.base, other.base);
+        merge_vec(&mut self.include, other.include);
+        merge_vec(&mut self.exclude, other.exclude);
+    }
+}
+
+impl Environment {
+    // Values set in `other` replace the ones in `self`.
+    fn merge(&mut self, other: Environment) {
+        merge_vec(&mut self.profiles, other.profiles);
+        merge_vec(&mut self.regions, other.regions);
+        self.role_arn = other.role_arn.or(self.role_arn.take());
+        self.cluster = other.cluster.or(self.cluster.take());
+        self.service = other.service.or(self.service.take());
+        self.lock_table = other.lock_table.or(self.lock_table.take());
+        self.pipelines.merge(other.pipelines);
+        self.buckets.merge(other.buckets);
+        self.repos.merge(other.repos);
+    }
+}
+
+impl ConfigFile {
+    pub fn parse(content: &str) -> AppResult<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn read(path: &Path) -> AppResult<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Self::parse(&content).map_err(|e| format!("Cannot parse {}: {}", path.display(), e).into())
+    }
+
+    // Later files override environments and fields of earlier ones.
+    fn merge(&mut self, other: ConfigFile) {
+        if other.default_env.is_some() {
+            self.default_env = other.default_env;
+        }
+        for (name, environment) in other.env {
+            self.env.entry(name).or_default().merge(environment);
+        }
+    }
+
+    // The user config, then the given file or the nearest project-local one.
+    pub fn load(explicit: Option<&Path>) -> AppResult<Self> {
+        let mut paths: Vec<PathBuf> = user_config_path()
+            .filter(|path| path.is_file())
+            .into_iter()
+            .collect();
+        // An explicitly given file has to exist, so it is not checked here.
+        match explicit {
+            Some(path) => paths.push(path.to_owned()),
+            None => paths.extend(project_config_path()),
+        }
+
+        let mut config = ConfigFile::default();
+        for path in paths {
+            debug!("Loading config file {}", path.display());
+            config.merge(ConfigFile::read(&path)?);
+        }
+        Ok(config)
+    }
+
+    // The requested environment, falling back to `default_env`, or an empty one without either.
+    pub fn environment(&self, name: Option<&str>) -> AppResult<Environment> {
+        match name.or(self.default_env.as_deref()) {
+            Some(name) => {
+                self.env.get(name).cloned().ok_or_else(|| {
+                    format!("Environment '{}' is not defined in config", name).into()
+                })
+            }
+            None => Ok(Environment::default()),
+        }
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("aws-toolkit").join("config.toml"))
+}
+
+fn project_config_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_environments() {
+        let mut config = ConfigFile::parse(
+            r#"
+            default_env = "prod"
+
+            [env.prod]
+            profile = "infra"
+            regions = ["eu-central-1", "us-east-2"]
+            cluster = "app"
+
+            [env.prod.pipelines]
+            include = "foo"
+            exclude = ["bar"]
+            "#,
+        )
+        .unwrap();
+        config.merge(
+            ConfigFile::parse(
+                r#"
+                [env.prod]
+                cluster = "web"
+
+                [env.prod.pipelines]
+                include = ["baz"]
+                "#,
+            )
+            .unwrap(),
+        );
+
+        let prod = config.environment(None).unwrap();
+        assert_eq!(prod.profiles, vec!["infra"]);
+        assert_eq!(prod.regions, vec!["eu-central-1", "us-east-2"]);
+        assert_eq!(prod.cluster.as_deref(), Some("web"));
+        assert_eq!(prod.pipelines.include, vec!["baz"]);
+        assert_eq!(prod.pipelines.exclude, vec!["bar"]);
+
+        assert!(config.environment(Some("dev")).is_err());
+        assert!(ConfigFile::parse("[env.prod]\nclustr = \"app\"").is_err());
+    }
+}
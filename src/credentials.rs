@@ -0,0 +1,285 @@
+use aws_config::Region;
+use aws_config::default_provider::credentials::DefaultCredentialsChain;
+use aws_config::sso::SsoCredentialsProvider;
+use aws_credential_types::Credentials;
+use aws_credential_types::provider::{
+    ProvideCredentials, SharedCredentialsProvider, error::CredentialsError, future,
+};
+use aws_sdk_sts::{Client as StsClient, Config as StsConfig};
+use chrono::Utc;
+use clap::Args;
+use log::debug;
+use std::io::Write;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+
+// Cached credentials are refreshed when they are about to expire within this window.
+const EXPIRY_BUFFER: Duration = Duration::from_secs(300);
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct CredentialArgs {
+    #[clap(
+        long,
+        global = true,
+        help = "IAM role ARN to assume on top of the profile credentials"
+    )]
+    pub role_arn: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        requires = "role_arn",
+        help = "External ID passed to AssumeRole"
+    )]
+    pub external_id: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        requires = "role_arn",
+        help = "Session name passed to AssumeRole"
+    )]
+    pub session_name: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        requires = "role_arn",
+        help = "MFA device serial number or ARN, prompts for a token code"
+    )]
+    pub mfa_serial: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        requires = "role_arn",
+        help = "Assumed role session duration in seconds"
+    )]
+    pub session_duration: Option<u32>,
+
+    #[clap(
+        long,
+        global = true,
+        requires_all = ["sso_account_id", "sso_role_name"],
+        help = "IAM Identity Center start URL, uses the token cached by `aws sso login`"
+    )]
+    pub sso_start_url: Option<String>,
+
+    #[clap(long, global = true, requires = "sso_start_url")]
+    pub sso_account_id: Option<String>,
+
+    #[clap(long, global = true, requires = "sso_start_url")]
+    pub sso_role_name: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        requires = "sso_start_url",
+        help = "IAM Identity Center region"
+    )]
+    pub sso_region: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        requires = "sso_start_url",
+        help = "sso-session name from ~/.aws/config"
+    )]
+    pub sso_session: Option<String>,
+}
+
+// Resolves base credentials once and hands out clones until they are about to expire,
+// so every client built in one run shares a single session and a single MFA prompt.
+#[derive(Debug)]
+struct CachedCredentialsProvider<P> {
+    inner: P,
+    cached: Mutex<Option<Credentials>>,
+}
+
+impl<P> CachedCredentialsProvider<P> {
+    fn new(inner: P) -> Self {
+        Self {
+            inner,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+fn is_fresh(credentials: &Credentials) -> bool {
+    credentials
+        .expiry()
+        .is_none_or(|expiry| expiry > SystemTime::now() + EXPIRY_BUFFER)
+}
+
+impl<P: ProvideCredentials> ProvideCredentials for CachedCredentialsProvider<P> {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(async move {
+            let mut cached = self.cached.lock().await;
+            if let Some(credentials) = cached.as_ref()
+                && is_fresh(credentials)
+            {
+                return Ok(credentials.clone());
+            }
+            let credentials = self.inner.provide_credentials().await?;
+            *cached = Some(credentials.clone());
+            Ok(credentials)
+        })
+    }
+}
+
+// AssumeRole with optional external ID and MFA token, which the aws-config provider lacks.
+#[derive(Debug)]
+struct AssumeRoleCredentialsProvider {
+    client: StsClient,
+    role_arn: String,
+    external_id: Option<String>,
+    session_name: String,
+    mfa_serial: Option<String>,
+    session_duration: Option<u32>,
+}
+
+impl AssumeRoleCredentialsProvider {
+    async fn assume_role(&self) -> aws_credential_types::provider::Result {
+        let token_code = match &self.mfa_serial {
+            Some(serial) => Some(prompt_mfa_token(serial).map_err(CredentialsError::not_loaded)?),
+            None => None,
+        };
+
+        let output = self
+            .client
+            .assume_role()
+            .role_arn(&self.role_arn)
+            .role_session_name(&self.session_name)
+            .set_external_id(self.external_id.clone())
+            .set_serial_number(self.mfa_serial.clone())
+            .set_token_code(token_code)
+            .set_duration_seconds(self.session_duration.map(|d| d as i32))
+            .send()
+            .await
+            .map_err(CredentialsError::provider_error)?;
+        debug!("Assumed role: {:?}", output.assumed_role_user());
+
+        let credentials = output
+            .credentials()
+            .ok_or_else(|| CredentialsError::unhandled("AssumeRole returned no credentials"))?;
+
+        Ok(Credentials::new(
+            credentials.access_key_id(),
+            credentials.secret_access_key(),
+            Some(credentials.session_token().to_owned()),
+            SystemTime::try_from(*credentials.expiration()).ok(),
+            "AssumeRole",
+        ))
+    }
+}
+
+impl ProvideCredentials for AssumeRoleCredentialsProvider {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(self.assume_role())
+    }
+}
+
+fn prompt_mfa_token(serial: &str) -> std::io::Result<String> {
+    eprint!("Enter MFA code for {}: ", serial);
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_owned())
+}
+
+async fn base_credentials_provider(
+    region: &Region,
+    profile: &str,
+    args: &CredentialArgs,
+) -> SharedCredentialsProvider {
+    if let Some(start_url) = &args.sso_start_url {
+        let sso_region = args
+            .sso_region
+            .clone()
+            .map(Region::new)
+            .unwrap_or_else(|| region.clone());
+        let mut builder = SsoCredentialsProvider::builder()
+            .start_url(start_url)
+            .region(sso_region);
+        builder
+            .set_account_id(args.sso_account_id.clone())
+            .set_role_name(args.sso_role_name.clone())
+            .set_session_name(args.sso_session.clone());
+        return SharedCredentialsProvider::new(builder.build());
+    }
+
+    SharedCredentialsProvider::new(
+        DefaultCredentialsChain::builder()
+            .profile_name(profile)
+            .region(region.clone())
+            .build()
+            .await,
+    )
+}
+
+pub async fn initialize_credentials(
+    region: &Region,
+    profile: &str,
+    args: &CredentialArgs,
+) -> SharedCredentialsProvider {
+    let base = base_credentials_provider(region, profile, args).await;
+
+    let Some(role_arn) = &args.role_arn else {
+        return SharedCredentialsProvider::new(CachedCredentialsProvider::new(base));
+    };
+
+    let sts_config = StsConfig::builder()
+        .credentials_provider(CachedCredentialsProvider::new(base))
+        .region(region.clone())
+        .build();
+
+    let provider = AssumeRoleCredentialsProvider {
+        client: StsClient::from_conf(sts_config),
+        role_arn: role_arn.to_owned(),
+        external_id: args.external_id.clone(),
+        session_name: args
+            .session_name
+            .clone()
+            .unwrap_or_else(|| format!("aws-toolkit-{}", Utc::now().timestamp())),
+        mfa_serial: args.mfa_serial.clone(),
+        session_duration: args.session_duration,
+    };
+
+    SharedCredentialsProvider::new(CachedCredentialsProvider::new(provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_fresh() {
+        let static_credentials = Credentials::new("id", "secret", None, None, "test");
+        assert!(is_fresh(&static_credentials));
+
+        let expiring = Credentials::new(
+            "id",
+            "secret",
+            None,
+            Some(SystemTime::now() + Duration::from_secs(60)),
+            "test",
+        );
+        assert!(!is_fresh(&expiring));
+
+        let valid = Credentials::new(
+            "id",
+            "secret",
+            None,
+            Some(SystemTime::now() + Duration::from_secs(3600)),
+            "test",
+        );
+        assert!(is_fresh(&valid));
+    }
+}
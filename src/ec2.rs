@@ -1,12 +1,117 @@
 use crate::AppResult;
+use crate::audit::Audit;
+use crate::output::Tabular;
+use crate::retry::{RetryPolicy, retry};
+use crate::wait::{ResourceState, wait_until};
 use aws_sdk_ec2::Client;
-use aws_sdk_ec2::types::Filter;
-use aws_sdk_ec2::types::InstanceStateName::{ShuttingDown, Terminated};
-use aws_sdk_ec2::types::NatGatewayState::{Deleted, Deleting};
+use aws_sdk_ec2::types::InstanceStateName::{self, Running, ShuttingDown, Stopped, Terminated};
+use aws_sdk_ec2::types::NatGatewayState::{Available, Deleted, Deleting};
+use aws_sdk_ec2::types::{DomainType, Filter, Instance, ResourceType, Tag, TagSpecification};
+use log::warn;
+use serde::Serialize;
+use std::time::Duration;
 
-pub async fn get_nat_gateway_ids(client: &Client, cluster: &str) -> AppResult<Vec<String>> {
-    let mut nat_gateway_ids: Vec<String> = Vec::new();
-    let filter = Filter::builder().name("tag:Prefix").values(cluster).build();
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NatGateway {
+    pub id: String,
+    pub state: String,
+    pub subnet_id: Option<String>,
+    pub allocation_ids: Vec<String>,
+    pub tags: Vec<(String, String)>,
+}
+
+// Tags on the subnet of a deleted NAT gateway, the allocation ID of its Elastic IP and
+// its own tags, and on the route tables which pointed at it, the subnet.
+const NAT_ALLOCATION_TAG: &str = "aws-toolkit:nat-gateway";
+const NAT_TAG_PREFIX: &str = "aws-toolkit:nat-tag:";
+const NAT_SUBNET_TAG: &str = "aws-toolkit:nat-subnet";
+const DEFAULT_ROUTE: &str = "0.0.0.0/0";
+
+// A NAT gateway deleted by an earlier run, as recorded on its subnet and route tables.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NatGatewayRecord {
+    pub subnet_id: String,
+    pub allocation_id: Option<String>,
+    pub tags: Vec<(String, String)>,
+    pub route_table_ids: Vec<String>,
+}
+
+impl Tabular for NatGateway {
+    fn headers() -> Vec<&'static str> {
+        vec!["NAT GATEWAY", "STATE", "SUBNET"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.state.clone(),
+            self.subnet_id.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Ec2Instance {
+    pub id: String,
+    pub state: String,
+    pub instance_type: Option<String>,
+    pub policy: InstancePolicy,
+}
+
+// What scale-in-ecs does with an instance, from its `ScaleIn` tag. Instances without the
+// tag are terminated.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstancePolicy {
+    Terminate,
+    Stop,
+    Skip,
+}
+
+const SCALE_IN_TAG: &str = "ScaleIn";
+// Tag on instances stopped by scale-in-ecs, which --scaleup starts again.
+const STOPPED_TAG: &str = "aws-toolkit:stopped";
+
+impl InstancePolicy {
+    // An unknown value keeps the instance, rather than terminating what was meant to stay.
+    fn parse(instance_id: &str, value: Option<&str>) -> Self {
+        match value.map(str::to_lowercase).as_deref() {
+            None | Some("terminate") => InstancePolicy::Terminate,
+            Some("stop") => InstancePolicy::Stop,
+            Some("skip") => InstancePolicy::Skip,
+            Some(other) => {
+                warn!(
+                    "{} has an unknown {} tag {:?}, skipping it",
+                    instance_id, SCALE_IN_TAG, other
+                );
+                InstancePolicy::Skip
+            }
+        }
+    }
+}
+
+impl Tabular for Ec2Instance {
+    fn headers() -> Vec<&'static str> {
+        vec!["INSTANCE", "STATE", "TYPE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.state.clone(),
+            self.instance_type.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+// Resources of a cluster carry its name in the `Prefix` tag.
+pub fn prefix_filter(cluster: &str) -> Filter {
+    Filter::builder().name("tag:Prefix").values(cluster).build()
+}
+
+pub async fn get_nat_gateways(client: &Client, cluster: &str) -> AppResult<Vec<NatGateway>> {
+    let mut nat_gateways: Vec<NatGateway> = Vec::new();
+    let filter = prefix_filter(cluster);
 
     let mut nat_gateway_stream = client
         .describe_nat_gateways()
@@ -15,103 +120,821 @@ pub async fn get_nat_gateway_ids(client: &Client, cluster: &str) -> AppResult<Ve
         .into_paginator()
         .send();
 
-    while let Some(nat_gateways) = nat_gateway_stream.next().await {
-        nat_gateway_ids.extend(
-            nat_gateways?
-                .nat_gateways()
-                .iter()
-                .filter_map(|nat_gateway| {
-                    if [Deleted, Deleting].contains(nat_gateway.state()?) {
-                        return None;
-                    }
-
-                    let get_id_string = || nat_gateway.nat_gateway_id().map(ToOwned::to_owned);
+    while let Some(output) = nat_gateway_stream.next().await {
+        nat_gateways.extend(output?.nat_gateways().iter().filter_map(|nat_gateway| {
+            let state = nat_gateway.state()?;
+            if [Deleted, Deleting].contains(state) {
+                return None;
+            }
 
-                    nat_gateway
+            let to_nat_gateway = || {
+                Some(NatGateway {
+                    id: nat_gateway.nat_gateway_id()?.to_owned(),
+                    state: state.as_str().to_owned(),
+                    subnet_id: nat_gateway.subnet_id().map(ToOwned::to_owned),
+                    allocation_ids: nat_gateway
+                        .nat_gateway_addresses()
+                        .iter()
+                        .filter_map(|address| address.allocation_id())
+                        .map(ToOwned::to_owned)
+                        .collect(),
+                    tags: nat_gateway
                         .tags()
                         .iter()
-                        .find_map(|tag| tag.value().filter(|v| v.contains(cluster)))
-                        .and_then(|_| get_id_string())
-                }),
-        );
+                        .filter_map(|tag| Some((tag.key()?.to_owned(), tag.value()?.to_owned())))
+                        .collect(),
+                })
+            };
+
+            nat_gateway
+                .tags()
+                .iter()
+                .find_map(|tag| tag.value().filter(|v| v.contains(cluster)))
+                .and_then(|_| to_nat_gateway())
+        }));
+    }
+    Ok(nat_gateways)
+}
+
+pub async fn delete_nat_gateway(client: &Client, audit: &Audit, gateway_id: &str) -> AppResult<()> {
+    audit
+        .record(
+            "ec2:DeleteNatGateway",
+            &[("nat_gateway_id", gateway_id)],
+            retry(&RetryPolicy::default(), "delete_nat_gateway", || {
+                client
+                    .delete_nat_gateway()
+                    .nat_gateway_id(gateway_id)
+                    .send()
+            }),
+        )
+        .await?;
+    Ok(())
+}
+
+fn tag(key: &str, value: &str) -> Tag {
+    Tag::builder().key(key).value(value).build()
+}
+
+fn tag_specification(resource_type: ResourceType, tags: &[(String, String)]) -> TagSpecification {
+    TagSpecification::builder()
+        .resource_type(resource_type)
+        .set_tags(Some(
+            tags.iter().map(|(key, value)| tag(key, value)).collect(),
+        ))
+        .build()
+}
+
+// Route tables with a route to the NAT gateway.
+pub async fn list_nat_gateway_route_tables(
+    client: &Client,
+    gateway_id: &str,
+) -> AppResult<Vec<String>> {
+    let output = client
+        .describe_route_tables()
+        .filters(
+            Filter::builder()
+                .name("route.nat-gateway-id")
+                .values(gateway_id)
+                .build(),
+        )
+        .send()
+        .await?;
+    Ok(output
+        .route_tables()
+        .iter()
+        .filter_map(|route_table| route_table.route_table_id())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+// Records the subnet, Elastic IP, tags and route tables of the NAT gateway before it is
+// deleted, so recreate_nat_gateway can bring it back. Tags reserved by AWS are not kept,
+// as they cannot be set on the new gateway.
+pub async fn record_nat_gateway(
+    client: &Client,
+    audit: &Audit,
+    nat_gateway: &NatGateway,
+) -> AppResult<()> {
+    let Some(subnet_id) = &nat_gateway.subnet_id else {
+        return Ok(());
+    };
+    let route_table_ids = list_nat_gateway_route_tables(client, &nat_gateway.id).await?;
+    let allocation_id = nat_gateway
+        .allocation_ids
+        .first()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut subnet_tags = vec![tag(NAT_ALLOCATION_TAG, &allocation_id)];
+    subnet_tags.extend(
+        nat_gateway
+            .tags
+            .iter()
+            .filter(|(key, _)| !key.starts_with("aws:"))
+            .map(|(key, value)| tag(&format!("{}{}", NAT_TAG_PREFIX, key), value)),
+    );
+    audit
+        .record(
+            "ec2:CreateTags",
+            &[
+                ("resource_id", subnet_id),
+                ("nat_gateway_id", &nat_gateway.id),
+                (NAT_ALLOCATION_TAG, &allocation_id),
+            ],
+            client
+                .create_tags()
+                .resources(subnet_id)
+                .set_tags(Some(subnet_tags))
+                .send(),
+        )
+        .await?;
+
+    if !route_table_ids.is_empty() {
+        audit
+            .record(
+                "ec2:CreateTags",
+                &[
+                    ("resource_ids", &route_table_ids.join(",")),
+                    (NAT_SUBNET_TAG, subnet_id),
+                ],
+                client
+                    .create_tags()
+                    .set_resources(Some(route_table_ids))
+                    .tags(tag(NAT_SUBNET_TAG, subnet_id))
+                    .send(),
+            )
+            .await?;
     }
-    Ok(nat_gateway_ids)
+    Ok(())
 }
 
-#[async_recursion::async_recursion]
-pub async fn delete_nat_gateway(client: &Client, gateway_id: &str) -> AppResult<()> {
-    match client
-        .delete_nat_gateway()
-        .nat_gateway_id(gateway_id)
+// NAT gateways of the cluster recorded by record_nat_gateway.
+pub async fn list_nat_gateway_records(
+    client: &Client,
+    cluster: &str,
+) -> AppResult<Vec<NatGatewayRecord>> {
+    let subnets = client
+        .describe_subnets()
+        .filters(
+            Filter::builder()
+                .name(format!("tag:{}Prefix", NAT_TAG_PREFIX))
+                .values(cluster)
+                .build(),
+        )
         .send()
-        .await
-    {
-        Ok(_) => Ok(()),
-        _ => {
-            tokio::time::sleep(std::time::Duration::from_secs(15)).await;
-            delete_nat_gateway(client, gateway_id).await?;
-            Ok(())
+        .await?;
+
+    let mut records = Vec::new();
+    for subnet in subnets.subnets() {
+        let Some(subnet_id) = subnet.subnet_id() else {
+            continue;
+        };
+        if !subnet
+            .tags()
+            .iter()
+            .any(|subnet_tag| subnet_tag.key() == Some(NAT_ALLOCATION_TAG))
+        {
+            continue;
+        }
+        let mut record = NatGatewayRecord {
+            subnet_id: subnet_id.to_owned(),
+            allocation_id: None,
+            tags: Vec::new(),
+            route_table_ids: Vec::new(),
+        };
+        for subnet_tag in subnet.tags() {
+            let (Some(key), Some(value)) = (subnet_tag.key(), subnet_tag.value()) else {
+                continue;
+            };
+            if key == NAT_ALLOCATION_TAG {
+                record.allocation_id = Some(value.to_owned()).filter(|id| !id.is_empty());
+            } else if let Some(key) = key.strip_prefix(NAT_TAG_PREFIX) {
+                record.tags.push((key.to_owned(), value.to_owned()));
+            }
+        }
+
+        let route_tables = client
+            .describe_route_tables()
+            .filters(
+                Filter::builder()
+                    .name(format!("tag:{}", NAT_SUBNET_TAG))
+                    .values(subnet_id)
+                    .build(),
+            )
+            .send()
+            .await?;
+        record.route_table_ids = route_tables
+            .route_tables()
+            .iter()
+            .filter_map(|route_table| route_table.route_table_id())
+            .map(ToOwned::to_owned)
+            .collect();
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// Elastic IPs of NAT gateways stay allocated, and billed, until they are released.
+pub async fn release_address(client: &Client, audit: &Audit, allocation_id: &str) -> AppResult<()> {
+    audit
+        .record(
+            "ec2:ReleaseAddress",
+            &[("allocation_id", allocation_id)],
+            client.release_address().allocation_id(allocation_id).send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// A released allocation is not found by the filter, while the AllocationIds parameter fails.
+async fn address_exists(client: &Client, allocation_id: &str) -> AppResult<bool> {
+    let output = client
+        .describe_addresses()
+        .filters(
+            Filter::builder()
+                .name("allocation-id")
+                .values(allocation_id)
+                .build(),
+        )
+        .send()
+        .await?;
+    Ok(!output.addresses().is_empty())
+}
+
+async fn allocate_address(
+    client: &Client,
+    audit: &Audit,
+    tags: &[(String, String)],
+) -> AppResult<String> {
+    let mut request = client.allocate_address().domain(DomainType::Vpc);
+    if !tags.is_empty() {
+        request = request.tag_specifications(tag_specification(ResourceType::ElasticIp, tags));
+    }
+    let output = audit
+        .record("ec2:AllocateAddress", &[("domain", "vpc")], request.send())
+        .await?;
+    Ok(output
+        .allocation_id()
+        .ok_or("AllocateAddress returned no allocation ID")?
+        .to_owned())
+}
+
+// Creates the recorded NAT gateway again, with its Elastic IP if it was kept or a new one.
+// Returns the ID of the new gateway.
+pub async fn create_nat_gateway(
+    client: &Client,
+    audit: &Audit,
+    record: &NatGatewayRecord,
+) -> AppResult<String> {
+    let allocation_id = match &record.allocation_id {
+        Some(allocation_id) if address_exists(client, allocation_id).await? => {
+            allocation_id.clone()
         }
+        _ => allocate_address(client, audit, &record.tags).await?,
+    };
+
+    let mut request = client
+        .create_nat_gateway()
+        .subnet_id(&record.subnet_id)
+        .allocation_id(&allocation_id);
+    if !record.tags.is_empty() {
+        request =
+            request.tag_specifications(tag_specification(ResourceType::Natgateway, &record.tags));
     }
+    let output = audit
+        .record(
+            "ec2:CreateNatGateway",
+            &[
+                ("subnet_id", &record.subnet_id),
+                ("allocation_id", &allocation_id),
+            ],
+            request.send(),
+        )
+        .await?;
+    Ok(output
+        .nat_gateway()
+        .and_then(|nat_gateway| nat_gateway.nat_gateway_id())
+        .ok_or("CreateNatGateway returned no NAT gateway ID")?
+        .to_owned())
 }
 
-pub async fn get_ec2_instances_ids(client: &Client, cluster: &str) -> AppResult<Vec<String>> {
-    let mut ec2_instances_ids: Vec<String> = Vec::new();
-    let prefix_filter = Filter::builder().name("tag:Prefix").values(cluster).build();
+// Points the default route of the route table at the NAT gateway. The route of a deleted
+// gateway stays as a blackhole and is replaced, a missing one is created.
+pub async fn point_default_route(
+    client: &Client,
+    audit: &Audit,
+    route_table_id: &str,
+    gateway_id: &str,
+) -> AppResult<()> {
+    let output = client
+        .describe_route_tables()
+        .route_table_ids(route_table_id)
+        .send()
+        .await?;
+    let has_default_route = output
+        .route_tables()
+        .iter()
+        .flat_map(|route_table| route_table.routes())
+        .any(|route| route.destination_cidr_block() == Some(DEFAULT_ROUTE));
 
-    let state_filter = Filter::builder()
-        .name("instance-state-name")
-        .values("running")
-        .build();
+    let parameters = [
+        ("route_table_id", route_table_id),
+        ("destination_cidr_block", DEFAULT_ROUTE),
+        ("nat_gateway_id", gateway_id),
+    ];
+    if has_default_route {
+        audit
+            .record(
+                "ec2:ReplaceRoute",
+                &parameters,
+                client
+                    .replace_route()
+                    .route_table_id(route_table_id)
+                    .destination_cidr_block(DEFAULT_ROUTE)
+                    .nat_gateway_id(gateway_id)
+                    .send(),
+            )
+            .await?;
+    } else {
+        audit
+            .record(
+                "ec2:CreateRoute",
+                &parameters,
+                client
+                    .create_route()
+                    .route_table_id(route_table_id)
+                    .destination_cidr_block(DEFAULT_ROUTE)
+                    .nat_gateway_id(gateway_id)
+                    .send(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+// Removes the record of a recreated NAT gateway from its subnet and route tables.
+pub async fn clear_nat_gateway_record(
+    client: &Client,
+    audit: &Audit,
+    record: &NatGatewayRecord,
+) -> AppResult<()> {
+    let mut subnet_tags = vec![Tag::builder().key(NAT_ALLOCATION_TAG).build()];
+    subnet_tags.extend(record.tags.iter().map(|(key, _)| {
+        Tag::builder()
+            .key(format!("{}{}", NAT_TAG_PREFIX, key))
+            .build()
+    }));
+    audit
+        .record(
+            "ec2:DeleteTags",
+            &[("resource_id", &record.subnet_id)],
+            client
+                .delete_tags()
+                .resources(&record.subnet_id)
+                .set_tags(Some(subnet_tags))
+                .send(),
+        )
+        .await?;
+
+    if !record.route_table_ids.is_empty() {
+        audit
+            .record(
+                "ec2:DeleteTags",
+                &[("resource_ids", &record.route_table_ids.join(","))],
+                client
+                    .delete_tags()
+                    .set_resources(Some(record.route_table_ids.clone()))
+                    .tags(Tag::builder().key(NAT_SUBNET_TAG).build())
+                    .send(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+fn to_ec2_instance(instance: &Instance, cluster: &str) -> Option<Ec2Instance> {
+    let state = instance
+        .state()
+        .expect("Cannot extract ec2 instance state.")
+        .name()
+        .expect("Cannot extract ec2 instance state name.");
+    if !instance.tags().iter().any(|t| {
+        t.value()
+            .expect("Cannot extract tag value.")
+            .contains(cluster)
+    }) || [ShuttingDown, Terminated].contains(state)
+    {
+        return None;
+    }
+
+    let id = instance
+        .instance_id()
+        .expect("Cannot extract EC2 Instance ID.");
+    let policy = instance
+        .tags()
+        .iter()
+        .find(|tag| tag.key() == Some(SCALE_IN_TAG))
+        .and_then(|tag| tag.value());
+    Some(Ec2Instance {
+        id: id.to_owned(),
+        state: state.as_str().to_owned(),
+        instance_type: instance
+            .instance_type()
+            .map(|instance_type| instance_type.as_str().to_owned()),
+        policy: InstancePolicy::parse(id, policy),
+    })
+}
+
+async fn list_instances(
+    client: &Client,
+    cluster: &str,
+    state_filters: Vec<Filter>,
+) -> AppResult<Vec<Ec2Instance>> {
+    let mut ec2_instances: Vec<Ec2Instance> = Vec::new();
+    let mut filters = vec![prefix_filter(cluster)];
+    filters.extend(state_filters);
 
     let mut ec2_instances_stream = client
         .describe_instances()
-        .filters(prefix_filter)
-        .filters(state_filter)
+        .set_filters(Some(filters))
         .max_results(100)
         .into_paginator()
         .send();
 
-    while let Some(ec2_instances) = ec2_instances_stream.next().await {
-        for reservation in ec2_instances?.reservations() {
-            for instance in reservation.instances() {
-                if instance.tags().iter().any(|t| {
-                    t.value()
-                        .expect("Cannot extract tag value.")
-                        .contains(cluster)
-                }) && ![ShuttingDown, Terminated].contains(
-                    instance
-                        .state()
-                        .expect("Cannot extract ec2 instance state.")
-                        .name()
-                        .expect("Cannot extract ec2 instance state name."),
-                ) {
-                    ec2_instances_ids.push(
-                        instance
-                            .instance_id()
-                            .expect("Cannot extract EC2 Instance ID.")
-                            .to_owned(),
-                    );
-                }
-            }
+    while let Some(ec2_instances_output) = ec2_instances_stream.next().await {
+        for reservation in ec2_instances_output?.reservations() {
+            ec2_instances.extend(
+                reservation
+                    .instances()
+                    .iter()
+                    .filter_map(|instance| to_ec2_instance(instance, cluster)),
+            );
         }
     }
-    Ok(ec2_instances_ids)
+    Ok(ec2_instances)
 }
 
-#[async_recursion::async_recursion]
-pub async fn terminate_ec2_instance(client: &Client, instance_id: &str) -> AppResult<()> {
-    match client
-        .terminate_instances()
-        .instance_ids(instance_id)
-        .send()
-        .await
-    {
-        Ok(_) => Ok(()),
-        _ => {
-            tokio::time::sleep(std::time::Duration::from_secs(15)).await;
-            terminate_ec2_instance(client, instance_id).await?;
-            Ok(())
-        }
+pub async fn get_ec2_instances(client: &Client, cluster: &str) -> AppResult<Vec<Ec2Instance>> {
+    let state_filter = Filter::builder()
+        .name("instance-state-name")
+        .values("running")
+        .build();
+    list_instances(client, cluster, vec![state_filter]).await
+}
+
+// Instances stopped by stop_ec2_instance.
+pub async fn get_stopped_ec2_instances(
+    client: &Client,
+    cluster: &str,
+) -> AppResult<Vec<Ec2Instance>> {
+    let state_filter = Filter::builder()
+        .name("instance-state-name")
+        .values("stopped")
+        .build();
+    let stopped_filter = Filter::builder()
+        .name("tag-key")
+        .values(STOPPED_TAG)
+        .build();
+    list_instances(client, cluster, vec![state_filter, stopped_filter]).await
+}
+
+pub async fn terminate_ec2_instance(
+    client: &Client,
+    audit: &Audit,
+    instance_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "ec2:TerminateInstances",
+            &[("instance_id", instance_id)],
+            retry(&RetryPolicy::default(), "terminate_instances", || {
+                client
+                    .terminate_instances()
+                    .instance_ids(instance_id)
+                    .send()
+            }),
+        )
+        .await?;
+    Ok(())
+}
+
+// Tags the instance before stopping it, so --scaleup knows which stopped instances to start.
+pub async fn stop_ec2_instance(client: &Client, audit: &Audit, instance_id: &str) -> AppResult<()> {
+    audit
+        .record(
+            "ec2:CreateTags",
+            &[("resource_id", instance_id), (STOPPED_TAG, "true")],
+            client
+                .create_tags()
+                .resources(instance_id)
+                .tags(tag(STOPPED_TAG, "true"))
+                .send(),
+        )
+        .await?;
+    audit
+        .record(
+            "ec2:StopInstances",
+            &[("instance_id", instance_id)],
+            retry(&RetryPolicy::default(), "stop_instances", || {
+                client.stop_instances().instance_ids(instance_id).send()
+            }),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn start_ec2_instance(
+    client: &Client,
+    audit: &Audit,
+    instance_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "ec2:StartInstances",
+            &[("instance_id", instance_id)],
+            retry(&RetryPolicy::default(), "start_instances", || {
+                client.start_instances().instance_ids(instance_id).send()
+            }),
+        )
+        .await?;
+    audit
+        .record(
+            "ec2:DeleteTags",
+            &[("resource_id", instance_id), ("tag_key", STOPPED_TAG)],
+            client
+                .delete_tags()
+                .resources(instance_id)
+                .tags(Tag::builder().key(STOPPED_TAG).build())
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Instances which are gone count as terminated.
+async fn wait_for_instances_state(
+    client: &Client,
+    instance_ids: &[String],
+    timeout: Duration,
+    what: &str,
+    target: InstanceStateName,
+) -> AppResult<Vec<ResourceState>> {
+    if instance_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let target = &target;
+    wait_until(what, timeout, || async move {
+        let output = client
+            .describe_instances()
+            .set_instance_ids(Some(instance_ids.to_vec()))
+            .send()
+            .await?;
+        let instances: Vec<_> = output
+            .reservations()
+            .iter()
+            .flat_map(|reservation| reservation.instances())
+            .collect();
+
+        Ok(instance_ids
+            .iter()
+            .map(|id| {
+                let state = instances
+                    .iter()
+                    .find(|instance| instance.instance_id() == Some(id.as_str()))
+                    .and_then(|instance| instance.state()?.name());
+                match state {
+                    Some(state) => ResourceState::new(id, state.as_str(), state == target),
+                    None => ResourceState::new(id, "missing", *target == Terminated),
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+pub async fn wait_for_instances_terminated(
+    client: &Client,
+    instance_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_for_instances_state(
+        client,
+        instance_ids,
+        timeout,
+        "EC2 instances terminating",
+        Terminated,
+    )
+    .await
+}
+
+pub async fn wait_for_instances_stopped(
+    client: &Client,
+    instance_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_for_instances_state(
+        client,
+        instance_ids,
+        timeout,
+        "EC2 instances stopping",
+        Stopped,
+    )
+    .await
+}
+
+pub async fn wait_for_instances_running(
+    client: &Client,
+    instance_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_for_instances_state(
+        client,
+        instance_ids,
+        timeout,
+        "EC2 instances starting",
+        Running,
+    )
+    .await
+}
+
+pub async fn wait_for_nat_gateways_deleted(
+    client: &Client,
+    gateway_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    if gateway_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    wait_until("NAT gateways deleting", timeout, || async move {
+        let output = client
+            .describe_nat_gateways()
+            .set_nat_gateway_ids(Some(gateway_ids.to_vec()))
+            .send()
+            .await?;
+
+        Ok(gateway_ids
+            .iter()
+            .map(|id| {
+                let state = output
+                    .nat_gateways()
+                    .iter()
+                    .find(|nat_gateway| nat_gateway.nat_gateway_id() == Some(id.as_str()))
+                    .and_then(|nat_gateway| nat_gateway.state());
+                match state {
+                    Some(state) => ResourceState::new(id, state.as_str(), *state == Deleted),
+                    None => ResourceState::new(id, "missing", true),
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+pub async fn wait_for_nat_gateways_available(
+    client: &Client,
+    gateway_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    if gateway_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    wait_until("NAT gateways pending", timeout, || async move {
+        let output = client
+            .describe_nat_gateways()
+            .set_nat_gateway_ids(Some(gateway_ids.to_vec()))
+            .send()
+            .await?;
+
+        Ok(gateway_ids
+            .iter()
+            .map(|id| {
+                let state = output
+                    .nat_gateways()
+                    .iter()
+                    .find(|nat_gateway| nat_gateway.nat_gateway_id() == Some(id.as_str()))
+                    .and_then(|nat_gateway| nat_gateway.state());
+                match state {
+                    Some(state) => ResourceState::new(id, state.as_str(), *state == Available),
+                    None => ResourceState::new(id, "missing", false),
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+pub async fn list_regions(client: &Client) -> AppResult<Vec<String>> {
+    let output = client.describe_regions().all_regions(false).send().await?;
+
+    let mut regions: Vec<String> = output
+        .regions()
+        .iter()
+        .filter_map(|region| region.region_name().map(ToOwned::to_owned))
+        .collect();
+    regions.sort();
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_ec2::operation::describe_nat_gateways::DescribeNatGatewaysOutput;
+    use aws_sdk_ec2::operation::describe_route_tables::DescribeRouteTablesOutput;
+    use aws_sdk_ec2::operation::describe_subnets::DescribeSubnetsOutput;
+    use aws_sdk_ec2::types::{
+        NatGateway as SdkNatGateway, NatGatewayState, RouteTable, Subnet, Tag,
+    };
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+
+    fn nat_gateway(id: &str, state: NatGatewayState, prefix: &str) -> SdkNatGateway {
+        SdkNatGateway::builder()
+            .nat_gateway_id(id)
+            .state(state)
+            .subnet_id("subnet-1")
+            .tags(Tag::builder().key("Prefix").value(prefix).build())
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_get_nat_gateways_skips_deleted() {
+        let rule = mock!(Client::describe_nat_gateways).then_output(|| {
+            DescribeNatGatewaysOutput::builder()
+                .nat_gateways(nat_gateway("nat-1", NatGatewayState::Available, "app"))
+                .nat_gateways(nat_gateway("nat-2", NatGatewayState::Deleted, "app"))
+                .nat_gateways(nat_gateway("nat-3", NatGatewayState::Deleting, "app"))
+                .nat_gateways(nat_gateway("nat-4", NatGatewayState::Available, "web"))
+                .nat_gateways(nat_gateway("nat-5", NatGatewayState::Pending, "app"))
+                .build()
+        });
+        let client = mock_client!(aws_sdk_ec2, RuleMode::Sequential, [&rule]);
+
+        let nat_gateways = get_nat_gateways(&client, "app").await.unwrap();
+        let ids: Vec<_> = nat_gateways.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["nat-1", "nat-5"]);
+        assert_eq!(nat_gateways[1].state, "pending");
+    }
+
+    #[tokio::test]
+    async fn test_list_nat_gateway_records() {
+        let subnets = mock!(Client::describe_subnets).then_output(|| {
+            DescribeSubnetsOutput::builder()
+                .subnets(
+                    Subnet::builder()
+                        .subnet_id("subnet-1")
+                        .tags(Tag::builder().key("Name").value("app-public").build())
+                        .tags(
+                            Tag::builder()
+                                .key(NAT_ALLOCATION_TAG)
+                                .value("eipalloc-1")
+                                .build(),
+                        )
+                        .tags(
+                            Tag::builder()
+                                .key("aws-toolkit:nat-tag:Prefix")
+                                .value("app")
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+        });
+        let route_tables = mock!(Client::describe_route_tables).then_output(|| {
+            DescribeRouteTablesOutput::builder()
+                .route_tables(RouteTable::builder().route_table_id("rtb-1").build())
+                .build()
+        });
+        let client = mock_client!(aws_sdk_ec2, RuleMode::Sequential, [&subnets, &route_tables]);
+
+        let records = list_nat_gateway_records(&client, "app").await.unwrap();
+        assert_eq!(
+            records,
+            vec![NatGatewayRecord {
+                subnet_id: "subnet-1".to_owned(),
+                allocation_id: Some("eipalloc-1".to_owned()),
+                tags: vec![("Prefix".to_owned(), "app".to_owned())],
+                route_table_ids: vec!["rtb-1".to_owned()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_instance_policy() {
+        assert_eq!(
+            InstancePolicy::parse("i-1", None),
+            InstancePolicy::Terminate
+        );
+        assert_eq!(
+            InstancePolicy::parse("i-1", Some("Stop")),
+            InstancePolicy::Stop
+        );
+        assert_eq!(
+            InstancePolicy::parse("i-1", Some("skip")),
+            InstancePolicy::Skip
+        );
+        assert_eq!(
+            InstancePolicy::parse("i-1", Some("hibernate")),
+            InstancePolicy::Skip
+        );
     }
 }
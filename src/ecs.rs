@@ -1,14 +1,61 @@
 use crate::AppResult;
+use crate::audit::Audit;
+use crate::output::Tabular;
+use crate::retry::{RetryPolicy, retry};
+use crate::wait::{ResourceState, wait_until};
 use aws_sdk_ecs::Client;
+use aws_sdk_ecs::types::{Service, ServiceField, Tag};
 use log::debug;
+use serde::Serialize;
+use std::time::Duration;
 
-//#[async_recursion::async_recursion]
-pub async fn get_service_arns(
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EcsService {
+    pub name: String,
+    pub arn: String,
+    pub desired_count: i32,
+    pub running_count: i32,
+    pub target_groups: Vec<String>,
+    pub restore_count: Option<i32>,
+    pub restore_min_capacity: Option<i32>,
+}
+
+// Tag holding the desired count of a service from before it was scaled down.
+const DESIRED_COUNT_TAG: &str = "aws-toolkit:desired-count";
+// Tag holding the min capacity of the scalable target of a service from before its
+// scaling was suspended.
+const MIN_CAPACITY_TAG: &str = "aws-toolkit:min-capacity";
+
+fn tag_value(service: &Service, key: &str) -> Option<i32> {
+    service
+        .tags()
+        .iter()
+        .find(|tag| tag.key() == Some(key))
+        .and_then(|tag| tag.value()?.parse().ok())
+}
+
+impl Tabular for EcsService {
+    fn headers() -> Vec<&'static str> {
+        vec!["SERVICE", "DESIRED", "RUNNING", "ARN"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.desired_count.to_string(),
+            self.running_count.to_string(),
+            self.arn.clone(),
+        ]
+    }
+}
+
+// Services of the cluster with more than `desired_count` tasks desired.
+pub async fn list_services(
     client: &Client,
     cluster: &str,
     desired_count: i32,
-) -> AppResult<Vec<String>> {
-    let mut service_arns: Vec<String> = Vec::new();
+) -> AppResult<Vec<EcsService>> {
+    let mut ecs_services: Vec<EcsService> = Vec::new();
     let mut services_stream = client
         .list_services()
         .cluster(cluster)
@@ -34,66 +81,267 @@ pub async fn get_service_arns(
                 .describe_services()
                 .cluster(cluster)
                 .set_services(Some(relevant_arns_chunk.to_vec()))
+                .include(ServiceField::Tags)
                 .send()
                 .await?;
 
-            service_arns.extend(
+            ecs_services.extend(
                 services
                     .services()
                     .iter()
                     .filter(|service| service.desired_count > desired_count)
-                    .map(|service| {
-                        service
+                    .map(|service| EcsService {
+                        name: service.service_name().unwrap_or_default().to_owned(),
+                        arn: service
                             .service_arn()
                             .expect("Cannot extract service arn.")
-                            .to_owned()
+                            .to_owned(),
+                        desired_count: service.desired_count,
+                        running_count: service.running_count,
+                        target_groups: service
+                            .load_balancers()
+                            .iter()
+                            .filter_map(|load_balancer| load_balancer.target_group_arn())
+                            .map(ToOwned::to_owned)
+                            .collect(),
+                        restore_count: tag_value(service, DESIRED_COUNT_TAG),
+                        restore_min_capacity: tag_value(service, MIN_CAPACITY_TAG),
                     }),
             );
         }
     }
-    Ok(service_arns)
+    Ok(ecs_services)
 }
 
-#[async_recursion::async_recursion]
 pub async fn scale_down_service(
     client: &Client,
+    audit: &Audit,
     cluster: &str,
     service_arn: &str,
     desired_count: i32,
 ) -> AppResult<()> {
-    match client
-        .update_service()
-        .cluster(cluster)
-        .service(service_arn)
-        .desired_count(desired_count)
-        .send()
-        .await
-    {
-        Ok(_) => Ok(()),
-        _ => {
-            tokio::time::sleep(std::time::Duration::from_secs(15)).await;
-            scale_down_service(client, cluster, service_arn, desired_count).await?;
-            Ok(())
-        }
-    }
+    let desired_count_string = desired_count.to_string();
+    audit
+        .record(
+            "ecs:UpdateService",
+            &[
+                ("cluster", cluster),
+                ("service", service_arn),
+                ("desired_count", &desired_count_string),
+            ],
+            retry(&RetryPolicy::default(), "update_service", || {
+                client
+                    .update_service()
+                    .cluster(cluster)
+                    .service(service_arn)
+                    .desired_count(desired_count)
+                    .send()
+            }),
+        )
+        .await?;
+    Ok(())
 }
 
-#[async_recursion::async_recursion]
-pub async fn delete_service(client: &Client, cluster: &str, service_arn: &str) -> AppResult<()> {
-    match client
-        .delete_service()
-        .cluster(cluster)
-        .service(service_arn)
+// UpdateService is the same call in both directions.
+pub async fn scale_up_service(
+    client: &Client,
+    audit: &Audit,
+    cluster: &str,
+    service_arn: &str,
+    desired_count: i32,
+) -> AppResult<()> {
+    scale_down_service(client, audit, cluster, service_arn, desired_count).await
+}
+
+async fn tag_service(
+    client: &Client,
+    audit: &Audit,
+    service_arn: &str,
+    key: &str,
+    value: i32,
+) -> AppResult<()> {
+    let value = value.to_string();
+    audit
+        .record(
+            "ecs:TagResource",
+            &[("resource_arn", service_arn), (key, &value)],
+            client
+                .tag_resource()
+                .resource_arn(service_arn)
+                .tags(Tag::builder().key(key).value(&value).build())
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Remembers the desired count, so the service can be scaled up to it again.
+pub async fn tag_desired_count(
+    client: &Client,
+    audit: &Audit,
+    service_arn: &str,
+    desired_count: i32,
+) -> AppResult<()> {
+    tag_service(client, audit, service_arn, DESIRED_COUNT_TAG, desired_count).await
+}
+
+// Remembers the min capacity of the scalable target of the service.
+pub async fn tag_min_capacity(
+    client: &Client,
+    audit: &Audit,
+    service_arn: &str,
+    min_capacity: i32,
+) -> AppResult<()> {
+    tag_service(client, audit, service_arn, MIN_CAPACITY_TAG, min_capacity).await
+}
+
+// Names of the ASGs behind the capacity providers of the cluster.
+pub async fn capacity_provider_asgs(client: &Client, cluster: &str) -> AppResult<Vec<String>> {
+    let clusters = client.describe_clusters().clusters(cluster).send().await?;
+    let capacity_providers: Vec<String> = clusters
+        .clusters()
+        .iter()
+        .flat_map(|cluster| cluster.capacity_providers().to_vec())
+        .collect();
+    if capacity_providers.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let output = client
+        .describe_capacity_providers()
+        .set_capacity_providers(Some(capacity_providers))
         .send()
-        .await
-    {
-        Ok(_) => Ok(()),
-        _ => {
-            tokio::time::sleep(std::time::Duration::from_secs(15)).await;
-            delete_service(client, cluster, service_arn).await?;
-            Ok(())
+        .await?;
+    debug!("Capacity providers: {:?}", output);
+    Ok(output
+        .capacity_providers()
+        .iter()
+        .filter_map(|capacity_provider| capacity_provider.auto_scaling_group_provider())
+        .map(|provider| {
+            let arn = provider.auto_scaling_group_arn();
+            arn.split_once("autoScalingGroupName/")
+                .map_or(arn, |(_, name)| name)
+                .to_owned()
+        })
+        .collect())
+}
+
+pub async fn delete_service(
+    client: &Client,
+    audit: &Audit,
+    cluster: &str,
+    service_arn: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "ecs:DeleteService",
+            &[("cluster", cluster), ("service", service_arn)],
+            retry(&RetryPolicy::default(), "delete_service", || {
+                client
+                    .delete_service()
+                    .cluster(cluster)
+                    .service(service_arn)
+                    .send()
+            }),
+        )
+        .await?;
+    Ok(())
+}
+
+// Service name from `arn:aws:ecs:<region>:<account>:service/<cluster>/<name>`.
+fn service_name(arn: &str) -> &str {
+    arn.rsplit('/').next().unwrap_or(arn)
+}
+
+// Current state of the services, ten at a time as that is what a single describe call takes.
+// Services which no longer exist are reported as `missing`.
+async fn describe_service_states(
+    client: &Client,
+    cluster: &str,
+    service_arns: &[String],
+    converged: impl Fn(&Service) -> (String, bool),
+) -> AppResult<Vec<ResourceState>> {
+    let mut states = Vec::new();
+    for chunk in service_arns.chunks(10) {
+        let output = client
+            .describe_services()
+            .cluster(cluster)
+            .set_services(Some(chunk.to_vec()))
+            .send()
+            .await?;
+        for arn in chunk {
+            let state = match output
+                .services()
+                .iter()
+                .find(|service| service.service_arn() == Some(arn.as_str()))
+            {
+                Some(service) => {
+                    let (state, done) = converged(service);
+                    ResourceState::new(service_name(arn), state, done)
+                }
+                None => ResourceState::new(service_name(arn), "missing", true),
+            };
+            states.push(state);
         }
     }
+    Ok(states)
+}
+
+// Waits until no tasks of the scaled down services are left.
+pub async fn wait_for_services_scaled_down(
+    client: &Client,
+    cluster: &str,
+    service_arns: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("ECS services scaling down", timeout, || {
+        describe_service_states(client, cluster, service_arns, |service| {
+            let tasks = service.running_count() + service.pending_count();
+            if tasks == 0 {
+                ("scaled-down".to_owned(), true)
+            } else {
+                (format!("{} tasks left", tasks), false)
+            }
+        })
+    })
+    .await
+}
+
+// Waits until every desired task of the scaled up services is running.
+pub async fn wait_for_services_scaled_up(
+    client: &Client,
+    cluster: &str,
+    service_arns: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("ECS services scaling up", timeout, || {
+        describe_service_states(client, cluster, service_arns, |service| {
+            let (running, desired) = (service.running_count(), service.desired_count());
+            if running >= desired && service.pending_count() == 0 {
+                ("scaled-up".to_owned(), true)
+            } else {
+                (format!("{} of {} tasks running", running, desired), false)
+            }
+        })
+    })
+    .await
+}
+
+// Waits until deleted services finished draining and are inactive.
+pub async fn wait_for_services_deleted(
+    client: &Client,
+    cluster: &str,
+    service_arns: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("ECS services draining", timeout, || {
+        describe_service_states(client, cluster, service_arns, |service| {
+            let status = service.status().unwrap_or("unknown").to_lowercase();
+            let inactive = status == "inactive";
+            (status, inactive)
+        })
+    })
+    .await
 }
 
 pub async fn get_service_arn(
@@ -181,3 +429,95 @@ pub async fn get_container_arn(
         .ec2_instance_id
         .expect("No EC2 instance found!"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_ecs::config::retry::RetryConfig;
+    use aws_sdk_ecs::operation::describe_services::DescribeServicesOutput;
+    use aws_sdk_ecs::operation::list_services::ListServicesOutput;
+    use aws_sdk_ecs::operation::update_service::UpdateServiceOutput;
+    use aws_sdk_ecs::types::Service;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+
+    const ARN: &str = "arn:aws:ecs:eu-central-1:123456789012:service";
+
+    fn service(name: &str, desired_count: i32) -> Service {
+        Service::builder()
+            .service_name(name)
+            .service_arn(format!("{}/app/{}", ARN, name))
+            .desired_count(desired_count)
+            .running_count(desired_count)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_list_services_across_pages() {
+        // Calls in order: first page, its services, second page, its services.
+        let first_page = mock!(Client::list_services)
+            .match_requests(|req| req.next_token().is_none())
+            .then_output(|| {
+                ListServicesOutput::builder()
+                    .service_arns(format!("{}/app/auth", ARN))
+                    .service_arns(format!("{}/other/auth", ARN))
+                    .next_token("page-2")
+                    .build()
+            });
+        let first_services = mock!(Client::describe_services).then_output(|| {
+            DescribeServicesOutput::builder()
+                .services(service("auth", 2))
+                .build()
+        });
+        let second_page = mock!(Client::list_services)
+            .match_requests(|req| req.next_token() == Some("page-2"))
+            .then_output(|| {
+                ListServicesOutput::builder()
+                    .service_arns(format!("{}/app/web", ARN))
+                    .build()
+            });
+        let second_services = mock!(Client::describe_services).then_output(|| {
+            DescribeServicesOutput::builder()
+                .services(service("web", 0))
+                .build()
+        });
+        let client = mock_client!(
+            aws_sdk_ecs,
+            RuleMode::Sequential,
+            [&first_page, &first_services, &second_page, &second_services]
+        );
+
+        let services = list_services(&client, "app", 0).await.unwrap();
+        assert_eq!(
+            services,
+            vec![EcsService {
+                name: "auth".to_owned(),
+                arn: format!("{}/app/auth", ARN),
+                desired_count: 2,
+                running_count: 2,
+                target_groups: Vec::new(),
+                restore_count: None,
+                restore_min_capacity: None,
+            }]
+        );
+        assert_eq!(second_services.num_calls(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_scale_down_service_retries_server_errors() {
+        let rule = mock!(Client::update_service)
+            .sequence()
+            .http_status(503, None)
+            .times(2)
+            .output(|| UpdateServiceOutput::builder().build())
+            .build();
+        // Retries are left to our policy, not to the SDK.
+        let client = mock_client!(aws_sdk_ecs, RuleMode::Sequential, [&rule], |config| {
+            config.retry_config(RetryConfig::disabled())
+        });
+
+        scale_down_service(&client, &Audit::disabled(), "app", "auth", 0)
+            .await
+            .unwrap();
+        assert_eq!(rule.num_calls(), 3);
+    }
+}
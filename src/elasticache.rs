@@ -1,8 +1,35 @@
-use crate::AppResult;
+use crate::audit::Audit;
+use crate::output::Tabular;
+use crate::wait::{ResourceState, wait_until};
+use crate::{AppResult, final_snapshot_name, sanitize_string};
 use aws_sdk_elasticache::Client;
+use aws_sdk_elasticache::types::Tag;
+use chrono::Utc;
 use log::debug;
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
 
-pub async fn list_replication_groups(client: &Client, cluster: &str) -> AppResult<Vec<String>> {
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReplicationGroup {
+    pub id: String,
+    pub status: String,
+}
+
+impl Tabular for ReplicationGroup {
+    fn headers() -> Vec<&'static str> {
+        vec!["REPLICATION GROUP", "STATUS"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.id.clone(), self.status.clone()]
+    }
+}
+
+pub async fn list_replication_groups(
+    client: &Client,
+    cluster: &str,
+) -> AppResult<Vec<ReplicationGroup>> {
     let mut replication_groups = Vec::new();
     let mut replication_groups_stream = client
         .describe_replication_groups()
@@ -14,10 +41,13 @@ pub async fn list_replication_groups(client: &Client, cluster: &str) -> AppResul
         debug!("Replication Groups: {:?}", replication_group);
         replication_groups.extend(replication_group?.replication_groups().iter().filter_map(
             |group| {
-                if group.replication_group_id()?.contains(cluster)
-                    && group.status()?.contains("available")
-                {
-                    group.replication_group_id.to_owned()
+                let id = group.replication_group_id()?;
+                let status = group.status()?;
+                if id.contains(cluster) && status.contains("available") {
+                    Some(ReplicationGroup {
+                        id: id.to_owned(),
+                        status: status.to_owned(),
+                    })
                 } else {
                     None
                 }
@@ -29,12 +59,719 @@ pub async fn list_replication_groups(client: &Client, cluster: &str) -> AppResul
 
 pub async fn delete_replication_group(
     client: &Client,
+    audit: &Audit,
     replication_group_id: &str,
+    final_snapshot: bool,
 ) -> AppResult<()> {
-    client
-        .delete_replication_group()
+    let final_snapshot = final_snapshot.then(|| final_snapshot_name(replication_group_id));
+    audit
+        .record(
+            "elasticache:DeleteReplicationGroup",
+            &[
+                ("replication_group_id", replication_group_id),
+                (
+                    "final_snapshot_identifier",
+                    final_snapshot.as_deref().unwrap_or_default(),
+                ),
+            ],
+            client
+                .delete_replication_group()
+                .replication_group_id(replication_group_id)
+                .set_final_snapshot_identifier(final_snapshot.clone())
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ServerlessCache {
+    pub name: String,
+    pub status: String,
+    pub engine: Option<String>,
+}
+
+impl Tabular for ServerlessCache {
+    fn headers() -> Vec<&'static str> {
+        vec!["SERVERLESS CACHE", "STATUS", "ENGINE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.status.clone(),
+            self.engine.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+// A cache cluster outside of any replication group, e.g. Memcached or a single node.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CacheCluster {
+    pub id: String,
+    pub status: String,
+    pub engine: Option<String>,
+    pub node_type: Option<String>,
+}
+
+impl Tabular for CacheCluster {
+    fn headers() -> Vec<&'static str> {
+        vec!["CACHE CLUSTER", "STATUS", "ENGINE", "NODE TYPE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.status.clone(),
+            self.engine.clone().unwrap_or_default(),
+            self.node_type.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+// Memcached keeps no data worth a snapshot and cannot take one.
+fn supports_snapshots(engine: Option<&str>) -> bool {
+    engine != Some("memcached")
+}
+
+pub async fn list_serverless_caches(
+    client: &Client,
+    cluster: &str,
+) -> AppResult<Vec<ServerlessCache>> {
+    let mut serverless_caches = Vec::new();
+    let mut serverless_caches_stream = client
+        .describe_serverless_caches()
+        .max_results(50)
+        .into_paginator()
+        .send();
+
+    while let Some(output) = serverless_caches_stream.next().await {
+        debug!("Serverless Caches: {:?}", output);
+        serverless_caches.extend(output?.serverless_caches().iter().filter_map(|cache| {
+            let name = cache.serverless_cache_name()?;
+            let status = cache.status()?;
+            if !name.contains(cluster) || status != "available" {
+                return None;
+            }
+            Some(ServerlessCache {
+                name: name.to_owned(),
+                status: status.to_owned(),
+                engine: cache.engine().map(ToOwned::to_owned),
+            })
+        }));
+    }
+    Ok(serverless_caches)
+}
+
+pub async fn list_cache_clusters(client: &Client, cluster: &str) -> AppResult<Vec<CacheCluster>> {
+    let mut cache_clusters = Vec::new();
+    let mut cache_clusters_stream = client
+        .describe_cache_clusters()
+        .max_records(100)
+        .into_paginator()
+        .send();
+
+    while let Some(output) = cache_clusters_stream.next().await {
+        debug!("Cache Clusters: {:?}", output);
+        cache_clusters.extend(output?.cache_clusters().iter().filter_map(|cache_cluster| {
+            // Members of replication groups go with their group.
+            if cache_cluster.replication_group_id().is_some() {
+                return None;
+            }
+            let id = cache_cluster.cache_cluster_id()?;
+            let status = cache_cluster.cache_cluster_status()?;
+            if !id.contains(cluster) || status != "available" {
+                return None;
+            }
+            Some(CacheCluster {
+                id: id.to_owned(),
+                status: status.to_owned(),
+                engine: cache_cluster.engine().map(ToOwned::to_owned),
+                node_type: cache_cluster.cache_node_type().map(ToOwned::to_owned),
+            })
+        }));
+    }
+    Ok(cache_clusters)
+}
+
+pub async fn delete_serverless_cache(
+    client: &Client,
+    audit: &Audit,
+    serverless_cache: &ServerlessCache,
+    final_snapshot: bool,
+) -> AppResult<()> {
+    let name = &serverless_cache.name;
+    let final_snapshot = (final_snapshot && supports_snapshots(serverless_cache.engine.as_deref()))
+        .then(|| final_snapshot_name(name));
+    audit
+        .record(
+            "elasticache:DeleteServerlessCache",
+            &[
+                ("serverless_cache_name", name),
+                (
+                    "final_snapshot_name",
+                    final_snapshot.as_deref().unwrap_or_default(),
+                ),
+            ],
+            client
+                .delete_serverless_cache()
+                .serverless_cache_name(name)
+                .set_final_snapshot_name(final_snapshot.clone())
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_cache_cluster(
+    client: &Client,
+    audit: &Audit,
+    cache_cluster: &CacheCluster,
+    final_snapshot: bool,
+) -> AppResult<()> {
+    let id = &cache_cluster.id;
+    let final_snapshot = (final_snapshot && supports_snapshots(cache_cluster.engine.as_deref()))
+        .then(|| final_snapshot_name(id));
+    audit
+        .record(
+            "elasticache:DeleteCacheCluster",
+            &[
+                ("cache_cluster_id", id),
+                (
+                    "final_snapshot_identifier",
+                    final_snapshot.as_deref().unwrap_or_default(),
+                ),
+            ],
+            client
+                .delete_cache_cluster()
+                .cache_cluster_id(id)
+                .set_final_snapshot_identifier(final_snapshot.clone())
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// States of resources being deleted, `describe` returns None once a resource is gone.
+async fn deletion_states<'a, F, Fut>(
+    ids: &'a [String],
+    describe: F,
+) -> AppResult<Vec<ResourceState>>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = AppResult<Option<String>>>,
+{
+    let mut states = Vec::with_capacity(ids.len());
+    for id in ids {
+        states.push(match describe(id).await? {
+            Some(status) => ResourceState::new(id, status, false),
+            None => ResourceState::new(id, "deleted", true),
+        });
+    }
+    Ok(states)
+}
+
+pub async fn wait_for_replication_groups_deleted(
+    client: &Client,
+    replication_group_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("Replication groups deleting", timeout, || {
+        deletion_states(replication_group_ids, |id| async move {
+            match client
+                .describe_replication_groups()
+                .replication_group_id(id)
+                .send()
+                .await
+            {
+                Ok(output) => Ok(Some(
+                    output
+                        .replication_groups()
+                        .first()
+                        .and_then(|group| group.status())
+                        .unwrap_or("unknown")
+                        .to_owned(),
+                )),
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_replication_group_not_found_fault()) =>
+                {
+                    Ok(None)
+                }
+                Err(err) => Err(err.into()),
+            }
+        })
+    })
+    .await
+}
+
+pub async fn wait_for_serverless_caches_deleted(
+    client: &Client,
+    serverless_cache_names: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("Serverless caches deleting", timeout, || {
+        deletion_states(serverless_cache_names, |name| async move {
+            match client
+                .describe_serverless_caches()
+                .serverless_cache_name(name)
+                .send()
+                .await
+            {
+                Ok(output) => Ok(Some(
+                    output
+                        .serverless_caches()
+                        .first()
+                        .and_then(|cache| cache.status())
+                        .unwrap_or("unknown")
+                        .to_owned(),
+                )),
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_serverless_cache_not_found_fault()) =>
+                {
+                    Ok(None)
+                }
+                Err(err) => Err(err.into()),
+            }
+        })
+    })
+    .await
+}
+
+pub async fn wait_for_cache_clusters_deleted(
+    client: &Client,
+    cache_cluster_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("Cache clusters deleting", timeout, || {
+        deletion_states(cache_cluster_ids, |id| async move {
+            match client
+                .describe_cache_clusters()
+                .cache_cluster_id(id)
+                .send()
+                .await
+            {
+                Ok(output) => Ok(Some(
+                    output
+                        .cache_clusters()
+                        .first()
+                        .and_then(|cache_cluster| cache_cluster.cache_cluster_status())
+                        .unwrap_or("unknown")
+                        .to_owned(),
+                )),
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_cache_cluster_not_found_fault()) =>
+                {
+                    Ok(None)
+                }
+                Err(err) => Err(err.into()),
+            }
+        })
+    })
+    .await
+}
+
+// Tags of a final snapshot holding what is needed to recreate its replication group.
+const TAG_PREFIX: &str = "aws-toolkit:";
+
+// A replication group deleted with a final snapshot, recreated from it later with the
+// same shape and placement.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HibernatedGroup {
+    pub id: String,
+    pub snapshot: String,
+    pub engine: Option<String>,
+    pub engine_version: Option<String>,
+    pub node_type: String,
+    pub cluster_mode: bool,
+    pub shards: i32,
+    pub replicas: i32,
+    pub parameter_group: Option<String>,
+    pub subnet_group: Option<String>,
+    pub security_groups: Vec<String>,
+}
+
+impl Tabular for HibernatedGroup {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "REPLICATION GROUP",
+            "SNAPSHOT",
+            "NODE TYPE",
+            "SHARDS",
+            "REPLICAS",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.snapshot.clone(),
+            self.node_type.clone(),
+            self.shards.to_string(),
+            self.replicas.to_string(),
+        ]
+    }
+}
+
+impl HibernatedGroup {
+    fn to_tags(&self) -> Vec<Tag> {
+        let mut values = vec![
+            ("replication-group", Some(self.id.clone())),
+            ("engine", self.engine.clone()),
+            ("engine-version", self.engine_version.clone()),
+            ("node-type", Some(self.node_type.clone())),
+            ("cluster-mode", Some(self.cluster_mode.to_string())),
+            ("shards", Some(self.shards.to_string())),
+            ("replicas", Some(self.replicas.to_string())),
+            ("parameter-group", self.parameter_group.clone()),
+            ("subnet-group", self.subnet_group.clone()),
+        ];
+        if !self.security_groups.is_empty() {
+            values.push(("security-groups", Some(self.security_groups.join(" "))));
+        }
+        values
+            .into_iter()
+            .filter_map(|(key, value)| {
+                Some(
+                    Tag::builder()
+                        .key(format!("{}{}", TAG_PREFIX, key))
+                        .value(value?)
+                        .build(),
+                )
+            })
+            .collect()
+    }
+
+    // None for snapshots which were not taken by a hibernation.
+    fn from_tags(snapshot: &str, tags: &[Tag]) -> Option<Self> {
+        let tag = |key: &str| {
+            tags.iter()
+                .find(|tag| tag.key() == Some(&format!("{}{}", TAG_PREFIX, key)))
+                .and_then(|tag| tag.value())
+                .map(ToOwned::to_owned)
+        };
+        Some(Self {
+            id: tag("replication-group")?,
+            snapshot: snapshot.to_owned(),
+            engine: tag("engine"),
+            engine_version: tag("engine-version"),
+            node_type: tag("node-type")?,
+            cluster_mode: tag("cluster-mode")? == "true",
+            shards: tag("shards")?.parse().ok()?,
+            replicas: tag("replicas")?.parse().ok()?,
+            parameter_group: tag("parameter-group"),
+            subnet_group: tag("subnet-group"),
+            security_groups: tag("security-groups")
+                .map(|groups| groups.split_whitespace().map(ToOwned::to_owned).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+// Reads the shape of the group from the group itself and placement from its first node.
+pub async fn describe_hibernation(
+    client: &Client,
+    replication_group_id: &str,
+) -> AppResult<HibernatedGroup> {
+    let output = client
+        .describe_replication_groups()
+        .replication_group_id(replication_group_id)
+        .send()
+        .await?;
+    let group = output
+        .replication_groups()
+        .first()
+        .ok_or_else(|| format!("Replication group {} not found", replication_group_id))?;
+
+    let node_groups = group.node_groups();
+    let replicas = node_groups
+        .first()
+        .map(|node_group| node_group.node_group_members().len() as i32 - 1)
+        .unwrap_or_default()
+        .max(0);
+
+    let first_node = group
+        .member_clusters()
+        .first()
+        .ok_or_else(|| format!("Replication group {} has no nodes", replication_group_id))?;
+    let output = client
+        .describe_cache_clusters()
+        .cache_cluster_id(first_node)
+        .send()
+        .await?;
+    let node = output
+        .cache_clusters()
+        .first()
+        .ok_or_else(|| format!("Cache cluster {} not found", first_node))?;
+
+    let snapshot_time = Utc::now().format("%Y-%m-%d-%H-%M");
+    Ok(HibernatedGroup {
+        id: replication_group_id.to_owned(),
+        snapshot: sanitize_string(&format!(
+            "{}-hibernated-{}",
+            replication_group_id, snapshot_time
+        )),
+        engine: node.engine().map(ToOwned::to_owned),
+        engine_version: node.engine_version().map(ToOwned::to_owned),
+        node_type: group
+            .cache_node_type()
+            .or(node.cache_node_type())
+            .ok_or("Unknown node type")?
+            .to_owned(),
+        cluster_mode: group.cluster_enabled().unwrap_or(false),
+        shards: node_groups.len().max(1) as i32,
+        replicas,
+        parameter_group: node
+            .cache_parameter_group()
+            .and_then(|group| group.cache_parameter_group_name())
+            .map(ToOwned::to_owned),
+        subnet_group: node.cache_subnet_group_name().map(ToOwned::to_owned),
+        security_groups: node
+            .security_groups()
+            .iter()
+            .filter_map(|group| group.security_group_id())
+            .map(ToOwned::to_owned)
+            .collect(),
+    })
+}
+
+// Deletes the group with a final snapshot and tags the snapshot once it shows up, the
+// group can be recreated from it with restore_replication_group.
+pub async fn hibernate_replication_group(
+    client: &Client,
+    audit: &Audit,
+    hibernation: &HibernatedGroup,
+    timeout: Duration,
+) -> AppResult<()> {
+    audit
+        .record(
+            "elasticache:DeleteReplicationGroup",
+            &[
+                ("replication_group_id", &hibernation.id),
+                ("final_snapshot_identifier", &hibernation.snapshot),
+            ],
+            client
+                .delete_replication_group()
+                .replication_group_id(&hibernation.id)
+                .final_snapshot_identifier(&hibernation.snapshot)
+                .send(),
+        )
+        .await?;
+
+    let describe_snapshot = || {
+        client
+            .describe_snapshots()
+            .snapshot_name(&hibernation.snapshot)
+            .send()
+    };
+    let states = wait_until("Final snapshots appearing", timeout, || async move {
+        let output = describe_snapshot().await?;
+        let status = output
+            .snapshots()
+            .first()
+            .map(|snapshot| snapshot.snapshot_status().unwrap_or("unknown"));
+        Ok(vec![ResourceState::new(
+            &hibernation.snapshot,
+            status.unwrap_or("missing"),
+            status.is_some(),
+        )])
+    })
+    .await?;
+    if states.iter().any(|state| !state.converged) {
+        return Err(format!(
+            "Final snapshot {} of {} did not show up, it is not tagged for restore",
+            hibernation.snapshot, hibernation.id
+        )
+        .into());
+    }
+
+    let output = describe_snapshot().await?;
+    let arn = output
+        .snapshots()
+        .first()
+        .and_then(|snapshot| snapshot.arn())
+        .ok_or_else(|| format!("Final snapshot {} has no ARN", hibernation.snapshot))?;
+    audit
+        .record(
+            "elasticache:AddTagsToResource",
+            &[("resource_name", arn)],
+            client
+                .add_tags_to_resource()
+                .resource_name(arn)
+                .set_tags(Some(hibernation.to_tags()))
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Available hibernation snapshots of the cluster, the newest one per replication group.
+pub async fn list_hibernated_groups(
+    client: &Client,
+    cluster: &str,
+) -> AppResult<Vec<HibernatedGroup>> {
+    let mut hibernated: Vec<HibernatedGroup> = Vec::new();
+    let mut snapshots_stream = client
+        .describe_snapshots()
+        .snapshot_source("manual")
+        .max_records(50)
+        .into_paginator()
+        .send();
+
+    while let Some(output) = snapshots_stream.next().await {
+        debug!("Snapshots: {:?}", output);
+        for snapshot in output?.snapshots() {
+            let (Some(name), Some(arn)) = (snapshot.snapshot_name(), snapshot.arn()) else {
+                continue;
+            };
+            if !name.contains(cluster) || snapshot.snapshot_status() != Some("available") {
+                continue;
+            }
+
+            let tags = client
+                .list_tags_for_resource()
+                .resource_name(arn)
+                .send()
+                .await?;
+            let Some(group) = HibernatedGroup::from_tags(name, tags.tag_list()) else {
+                continue;
+            };
+            // Snapshot names end with their creation time, so the greater one is newer.
+            match hibernated.iter_mut().find(|other| other.id == group.id) {
+                Some(other) if other.snapshot < group.snapshot => *other = group,
+                Some(_) => {}
+                None => hibernated.push(group),
+            }
+        }
+    }
+    Ok(hibernated)
+}
+
+pub async fn replication_group_exists(
+    client: &Client,
+    replication_group_id: &str,
+) -> AppResult<bool> {
+    match client
+        .describe_replication_groups()
         .replication_group_id(replication_group_id)
         .send()
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(err)
+            if err
+                .as_service_error()
+                .is_some_and(|e| e.is_replication_group_not_found_fault()) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub async fn restore_replication_group(
+    client: &Client,
+    audit: &Audit,
+    hibernation: &HibernatedGroup,
+) -> AppResult<()> {
+    let mut request = client
+        .create_replication_group()
+        .replication_group_id(&hibernation.id)
+        .replication_group_description(format!("Restored from {}", hibernation.snapshot))
+        .snapshot_name(&hibernation.snapshot)
+        .cache_node_type(&hibernation.node_type)
+        .set_engine(hibernation.engine.clone())
+        .set_engine_version(hibernation.engine_version.clone())
+        .set_cache_parameter_group_name(hibernation.parameter_group.clone())
+        .set_cache_subnet_group_name(hibernation.subnet_group.clone())
+        .set_security_group_ids(Some(hibernation.security_groups.clone()));
+    request = if hibernation.cluster_mode {
+        request
+            .num_node_groups(hibernation.shards)
+            .replicas_per_node_group(hibernation.replicas)
+    } else {
+        request.num_cache_clusters(hibernation.replicas + 1)
+    };
+
+    audit
+        .record(
+            "elasticache:CreateReplicationGroup",
+            &[
+                ("replication_group_id", &hibernation.id),
+                ("snapshot_name", &hibernation.snapshot),
+                ("cache_node_type", &hibernation.node_type),
+            ],
+            request.send(),
+        )
         .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_elasticache::operation::describe_cache_clusters::DescribeCacheClustersOutput;
+    use aws_sdk_elasticache::types::CacheCluster as SdkCacheCluster;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+
+    #[tokio::test]
+    async fn test_list_cache_clusters_outside_replication_groups() {
+        let rule = mock!(Client::describe_cache_clusters).then_output(|| {
+            DescribeCacheClustersOutput::builder()
+                .cache_clusters(
+                    SdkCacheCluster::builder()
+                        .cache_cluster_id("app-memcached")
+                        .cache_cluster_status("available")
+                        .engine("memcached")
+                        .build(),
+                )
+                .cache_clusters(
+                    SdkCacheCluster::builder()
+                        .cache_cluster_id("app-redis-001")
+                        .cache_cluster_status("available")
+                        .engine("redis")
+                        .replication_group_id("app-redis")
+                        .build(),
+                )
+                .build()
+        });
+        let client = mock_client!(aws_sdk_elasticache, RuleMode::Sequential, [&rule]);
+
+        let cache_clusters = list_cache_clusters(&client, "app").await.unwrap();
+        let ids: Vec<_> = cache_clusters.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["app-memcached"]);
+        assert!(!supports_snapshots(cache_clusters[0].engine.as_deref()));
+    }
+
+    #[test]
+    fn test_hibernation_tags() {
+        let hibernation = HibernatedGroup {
+            id: "app-redis".to_owned(),
+            snapshot: "app-redis-hibernated-2025-01-31-22-00".to_owned(),
+            engine: Some("redis".to_owned()),
+            engine_version: Some("7.1.0".to_owned()),
+            node_type: "cache.t4g.small".to_owned(),
+            cluster_mode: false,
+            shards: 1,
+            replicas: 1,
+            parameter_group: Some("default.redis7".to_owned()),
+            subnet_group: None,
+            security_groups: vec!["sg-1".to_owned(), "sg-2".to_owned()],
+        };
+        let tags = hibernation.to_tags();
+        assert!(
+            tags.iter()
+                .all(|tag| tag.key().unwrap().starts_with(TAG_PREFIX))
+        );
+        assert_eq!(
+            HibernatedGroup::from_tags(&hibernation.snapshot, &tags),
+            Some(hibernation)
+        );
+        assert_eq!(HibernatedGroup::from_tags("manual", &[]), None);
+    }
+}
@@ -1,8 +1,39 @@
 use crate::AppResult;
+use crate::audit::Audit;
+use crate::output::Tabular;
+use crate::wait::{ResourceState, wait_until};
 use aws_sdk_elasticloadbalancingv2::Client;
+use aws_sdk_elasticloadbalancingv2::types::{
+    Action, ActionTypeEnum, FixedResponseActionConfig, LoadBalancerAttribute,
+    PathPatternConditionConfig, RuleCondition, Tag, TargetDescription,
+};
 use log::debug;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
 
-pub async fn list_load_balancers(client: &Client, cluster: &str) -> AppResult<Vec<String>> {
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LoadBalancer {
+    pub name: String,
+    pub arn: String,
+    pub dns_name: Option<String>,
+}
+
+impl Tabular for LoadBalancer {
+    fn headers() -> Vec<&'static str> {
+        vec!["LOAD BALANCER", "DNS NAME", "ARN"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.dns_name.clone().unwrap_or_default(),
+            self.arn.clone(),
+        ]
+    }
+}
+
+pub async fn list_load_balancers(client: &Client, cluster: &str) -> AppResult<Vec<LoadBalancer>> {
     let mut load_balancers = Vec::new();
     let mut load_balancers_stream = client.describe_load_balancers().into_paginator().send();
 
@@ -10,21 +41,750 @@ pub async fn list_load_balancers(client: &Client, cluster: &str) -> AppResult<Ve
         debug!("Load Balancers: {:?}", load_balancer_output);
         load_balancers.extend(load_balancer_output?.load_balancers().iter().filter_map(
             |load_balancer| {
-                if !load_balancer.load_balancer_name()?.contains(cluster) {
+                let name = load_balancer.load_balancer_name()?;
+                if !name.contains(cluster) {
                     return None;
                 }
-                Some(load_balancer.load_balancer_arn()?.to_owned())
+                Some(LoadBalancer {
+                    name: name.to_owned(),
+                    arn: load_balancer.load_balancer_arn()?.to_owned(),
+                    dns_name: load_balancer.dns_name().map(ToOwned::to_owned),
+                })
             },
         ));
     }
     Ok(load_balancers)
 }
 
-pub async fn delete_load_balancer(client: &Client, load_balancer_arn: &str) -> AppResult<()> {
-    client
-        .delete_load_balancer()
+pub async fn delete_load_balancer(
+    client: &Client,
+    audit: &Audit,
+    load_balancer_arn: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "elasticloadbalancing:DeleteLoadBalancer",
+            &[("load_balancer_arn", load_balancer_arn)],
+            client
+                .delete_load_balancer()
+                .load_balancer_arn(load_balancer_arn)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// A listener rule, the default action of a listener is the rule with priority `default`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ListenerRule {
+    pub listener: String,
+    pub priority: String,
+    pub conditions: Vec<String>,
+    pub action: String,
+    pub target_groups: Vec<String>,
+}
+
+impl ListenerRule {
+    // `HTTPS:443 default` or `HTTPS:443 #10 path-pattern=/api/*`.
+    pub fn route(&self) -> String {
+        if self.conditions.is_empty() {
+            format!("{} {}", self.listener, self.priority)
+        } else {
+            format!(
+                "{} #{} {}",
+                self.listener,
+                self.priority,
+                self.conditions.join(" ")
+            )
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TargetHealth {
+    pub id: String,
+    pub port: Option<i32>,
+    pub state: String,
+    pub reason: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TargetGroup {
+    pub name: String,
+    pub arn: String,
+    pub protocol: Option<String>,
+    pub port: Option<i32>,
+    pub targets: Vec<TargetHealth>,
+}
+
+// Listeners, rules and target groups of a load balancer with the health of every target.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LoadBalancerInventory {
+    #[serde(flatten)]
+    pub load_balancer: LoadBalancer,
+    pub rules: Vec<ListenerRule>,
+    pub target_groups: Vec<TargetGroup>,
+}
+
+fn condition(condition: &RuleCondition) -> String {
+    let values = condition
+        .host_header_config()
+        .map(|config| config.values())
+        .or_else(|| {
+            condition
+                .path_pattern_config()
+                .map(|config| config.values())
+        })
+        .or_else(|| {
+            condition
+                .http_request_method_config()
+                .map(|config| config.values())
+        })
+        .or_else(|| condition.source_ip_config().map(|config| config.values()))
+        .unwrap_or(condition.values());
+    format!(
+        "{}={}",
+        condition.field().unwrap_or("unknown"),
+        values.join(",")
+    )
+}
+
+// Action types joined by `+` and the target groups any of them forwards to.
+fn actions(actions: &[Action]) -> (String, Vec<String>) {
+    let kinds: Vec<&str> = actions
+        .iter()
+        .filter_map(|action| action.r#type())
+        .map(|kind| kind.as_str())
+        .collect();
+    let mut target_groups: Vec<String> = Vec::new();
+    for action in actions {
+        let arns = action.target_group_arn().into_iter().chain(
+            action
+                .forward_config()
+                .map(|config| config.target_groups())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|tuple| tuple.target_group_arn()),
+        );
+        for arn in arns {
+            if !target_groups.iter().any(|known| known == arn) {
+                target_groups.push(arn.to_owned());
+            }
+        }
+    }
+    (kinds.join("+"), target_groups)
+}
+
+// Default actions and rules of every listener. Only HTTP and HTTPS listeners have rules.
+pub async fn list_listener_rules(
+    client: &Client,
+    load_balancer_arn: &str,
+) -> AppResult<Vec<ListenerRule>> {
+    let mut rules = Vec::new();
+    let mut listeners_stream = client
+        .describe_listeners()
+        .load_balancer_arn(load_balancer_arn)
+        .into_paginator()
+        .send();
+
+    while let Some(listeners_output) = listeners_stream.next().await {
+        debug!("Listeners: {:?}", listeners_output);
+        for listener in listeners_output?.listeners() {
+            let protocol = listener
+                .protocol()
+                .map(|protocol| protocol.as_str())
+                .unwrap_or("unknown");
+            let name = format!("{}:{}", protocol, listener.port().unwrap_or_default());
+            let (action, target_groups) = actions(listener.default_actions());
+            rules.push(ListenerRule {
+                listener: name.clone(),
+                priority: "default".to_owned(),
+                conditions: Vec::new(),
+                action,
+                target_groups,
+            });
+
+            let Some(listener_arn) = listener.listener_arn() else {
+                continue;
+            };
+            if !matches!(protocol, "HTTP" | "HTTPS") {
+                continue;
+            }
+            let mut rules_stream = client
+                .describe_rules()
+                .listener_arn(listener_arn)
+                .into_paginator()
+                .send();
+            while let Some(rules_output) = rules_stream.next().await {
+                debug!("Rules: {:?}", rules_output);
+                for rule in rules_output?.rules() {
+                    if rule.is_default().unwrap_or_default() {
+                        continue;
+                    }
+                    let (action, target_groups) = actions(rule.actions());
+                    rules.push(ListenerRule {
+                        listener: name.clone(),
+                        priority: rule.priority().unwrap_or_default().to_owned(),
+                        conditions: rule.conditions().iter().map(condition).collect(),
+                        action,
+                        target_groups,
+                    });
+                }
+            }
+        }
+    }
+    Ok(rules)
+}
+
+// Target groups attached to the load balancer, without their targets.
+pub async fn list_target_groups(
+    client: &Client,
+    load_balancer_arn: &str,
+) -> AppResult<Vec<TargetGroup>> {
+    let mut target_groups = Vec::new();
+    let mut target_groups_stream = client
+        .describe_target_groups()
+        .load_balancer_arn(load_balancer_arn)
+        .into_paginator()
+        .send();
+
+    while let Some(target_groups_output) = target_groups_stream.next().await {
+        debug!("Target Groups: {:?}", target_groups_output);
+        target_groups.extend(target_groups_output?.target_groups().iter().filter_map(
+            |target_group| {
+                Some(TargetGroup {
+                    name: target_group.target_group_name()?.to_owned(),
+                    arn: target_group.target_group_arn()?.to_owned(),
+                    protocol: target_group
+                        .protocol()
+                        .map(|protocol| protocol.as_str().to_owned()),
+                    port: target_group.port(),
+                    targets: Vec::new(),
+                })
+            },
+        ));
+    }
+    Ok(target_groups)
+}
+
+pub async fn describe_target_health(
+    client: &Client,
+    target_group_arn: &str,
+) -> AppResult<Vec<TargetHealth>> {
+    let health_output = client
+        .describe_target_health()
+        .target_group_arn(target_group_arn)
+        .send()
+        .await?;
+    debug!("Target Health: {:?}", health_output);
+
+    Ok(health_output
+        .target_health_descriptions()
+        .iter()
+        .filter_map(|description| {
+            let target = description.target()?;
+            let health = description.target_health();
+            Some(TargetHealth {
+                id: target.id()?.to_owned(),
+                port: target.port(),
+                state: health
+                    .and_then(|health| health.state())
+                    .map(|state| state.as_str())
+                    .unwrap_or("unknown")
+                    .to_owned(),
+                reason: health
+                    .and_then(|health| health.reason())
+                    .map(|reason| reason.as_str().to_owned()),
+                description: health
+                    .and_then(|health| health.description())
+                    .map(ToOwned::to_owned),
+            })
+        })
+        .collect())
+}
+
+pub async fn describe_inventory(
+    client: &Client,
+    load_balancer: LoadBalancer,
+) -> AppResult<LoadBalancerInventory> {
+    let rules = list_listener_rules(client, &load_balancer.arn).await?;
+    let mut target_groups = list_target_groups(client, &load_balancer.arn).await?;
+    for target_group in &mut target_groups {
+        target_group.targets = describe_target_health(client, &target_group.arn).await?;
+    }
+    Ok(LoadBalancerInventory {
+        load_balancer,
+        rules,
+        target_groups,
+    })
+}
+
+pub async fn disable_deletion_protection(
+    client: &Client,
+    audit: &Audit,
+    load_balancer_arn: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "elasticloadbalancing:ModifyLoadBalancerAttributes",
+            &[
+                ("load_balancer_arn", load_balancer_arn),
+                ("deletion_protection.enabled", "false"),
+            ],
+            client
+                .modify_load_balancer_attributes()
+                .load_balancer_arn(load_balancer_arn)
+                .attributes(
+                    LoadBalancerAttribute::builder()
+                        .key("deletion_protection.enabled")
+                        .value("false")
+                        .build(),
+                )
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Listener {
+    pub arn: String,
+    pub protocol: String,
+    pub port: i32,
+}
+
+impl Listener {
+    // Only HTTP and HTTPS listeners of application load balancers have rules.
+    pub fn has_rules(&self) -> bool {
+        matches!(self.protocol.as_str(), "HTTP" | "HTTPS")
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.protocol, self.port)
+    }
+}
+
+pub async fn list_listeners(client: &Client, load_balancer_arn: &str) -> AppResult<Vec<Listener>> {
+    let mut listeners = Vec::new();
+    let mut listeners_stream = client
+        .describe_listeners()
         .load_balancer_arn(load_balancer_arn)
+        .into_paginator()
+        .send();
+
+    while let Some(listeners_output) = listeners_stream.next().await {
+        debug!("Listeners: {:?}", listeners_output);
+        listeners.extend(listeners_output?.listeners().iter().filter_map(|listener| {
+            Some(Listener {
+                arn: listener.listener_arn()?.to_owned(),
+                protocol: listener.protocol()?.as_str().to_owned(),
+                port: listener.port().unwrap_or_default(),
+            })
+        }));
+    }
+    Ok(listeners)
+}
+
+pub async fn delete_listener(client: &Client, audit: &Audit, listener_arn: &str) -> AppResult<()> {
+    audit
+        .record(
+            "elasticloadbalancing:DeleteListener",
+            &[("listener_arn", listener_arn)],
+            client.delete_listener().listener_arn(listener_arn).send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Whether a load balancer still forwards to the target group, a deleted group is not in use.
+pub async fn target_group_in_use(client: &Client, target_group_arn: &str) -> AppResult<bool> {
+    match client
+        .describe_target_groups()
+        .target_group_arns(target_group_arn)
         .send()
+        .await
+    {
+        Ok(output) => Ok(output
+            .target_groups()
+            .iter()
+            .any(|target_group| !target_group.load_balancer_arns().is_empty())),
+        Err(err)
+            if err
+                .as_service_error()
+                .is_some_and(|e| e.is_target_group_not_found_exception()) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub async fn delete_target_group(
+    client: &Client,
+    audit: &Audit,
+    target_group_arn: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "elasticloadbalancing:DeleteTargetGroup",
+            &[("target_group_arn", target_group_arn)],
+            client
+                .delete_target_group()
+                .target_group_arn(target_group_arn)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Name from `arn:aws:elasticloadbalancing:...:loadbalancer/<type>/<name>/<id>` or
+// `arn:aws:elasticloadbalancing:...:targetgroup/<name>/<id>`.
+pub fn resource_name(arn: &str) -> &str {
+    arn.rsplit('/').nth(1).unwrap_or(arn)
+}
+
+// Waits until the deleted load balancers are gone.
+pub async fn wait_for_load_balancers_deleted(
+    client: &Client,
+    load_balancer_arns: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("Load balancers deleting", timeout, || async move {
+        let mut states = Vec::new();
+        for arn in load_balancer_arns {
+            let name = resource_name(arn);
+            let described = client
+                .describe_load_balancers()
+                .load_balancer_arns(arn)
+                .send()
+                .await;
+            let state = match described {
+                Ok(output) => {
+                    let code = output
+                        .load_balancers()
+                        .first()
+                        .and_then(|load_balancer| load_balancer.state()?.code())
+                        .map(|code| code.as_str())
+                        .unwrap_or("unknown");
+                    ResourceState::new(name, code, false)
+                }
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_load_balancer_not_found_exception()) =>
+                {
+                    ResourceState::new(name, "deleted", true)
+                }
+                Err(err) => return Err(err.into()),
+            };
+            states.push(state);
+        }
+        Ok(states)
+    })
+    .await
+}
+
+// Tag of the fixed-response rule which takes all traffic of a listener during maintenance.
+const MAINTENANCE_TAG: &str = "aws-toolkit:maintenance";
+
+// Answers every request with 503 ahead of all other rules. Priority 1 has to be free.
+pub async fn add_maintenance_rule(
+    client: &Client,
+    audit: &Audit,
+    listener_arn: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "elasticloadbalancing:CreateRule",
+            &[
+                ("listener_arn", listener_arn),
+                ("priority", "1"),
+                ("action", "fixed-response"),
+            ],
+            client
+                .create_rule()
+                .listener_arn(listener_arn)
+                .priority(1)
+                .conditions(
+                    RuleCondition::builder()
+                        .field("path-pattern")
+                        .path_pattern_config(
+                            PathPatternConditionConfig::builder().values("*").build(),
+                        )
+                        .build(),
+                )
+                .actions(
+                    Action::builder()
+                        .r#type(ActionTypeEnum::FixedResponse)
+                        .fixed_response_config(
+                            FixedResponseActionConfig::builder()
+                                .status_code("503")
+                                .content_type("text/plain")
+                                .message_body("Down for maintenance")
+                                .build(),
+                        )
+                        .build(),
+                )
+                .tags(Tag::builder().key(MAINTENANCE_TAG).value("true").build())
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Maintenance rules added by add_maintenance_rule to the listener.
+pub async fn list_maintenance_rules(client: &Client, listener_arn: &str) -> AppResult<Vec<String>> {
+    let mut rule_arns = Vec::new();
+    let mut rules_stream = client
+        .describe_rules()
+        .listener_arn(listener_arn)
+        .into_paginator()
+        .send();
+    while let Some(rules_output) = rules_stream.next().await {
+        debug!("Rules: {:?}", rules_output);
+        rule_arns.extend(
+            rules_output?
+                .rules()
+                .iter()
+                .filter(|rule| !rule.is_default().unwrap_or_default())
+                .filter_map(|rule| rule.rule_arn())
+                .map(ToOwned::to_owned),
+        );
+    }
+
+    let mut maintenance_rules = Vec::new();
+    // DescribeTags takes at most 20 resources.
+    for chunk in rule_arns.chunks(20) {
+        let output = client
+            .describe_tags()
+            .set_resource_arns(Some(chunk.to_vec()))
+            .send()
+            .await?;
+        maintenance_rules.extend(
+            output
+                .tag_descriptions()
+                .iter()
+                .filter(|description| {
+                    description
+                        .tags()
+                        .iter()
+                        .any(|tag| tag.key() == Some(MAINTENANCE_TAG))
+                })
+                .filter_map(|description| description.resource_arn())
+                .map(ToOwned::to_owned),
+        );
+    }
+    Ok(maintenance_rules)
+}
+
+pub async fn delete_rule(client: &Client, audit: &Audit, rule_arn: &str) -> AppResult<()> {
+    audit
+        .record(
+            "elasticloadbalancing:DeleteRule",
+            &[("rule_arn", rule_arn)],
+            client.delete_rule().rule_arn(rule_arn).send(),
+        )
         .await?;
     Ok(())
 }
+
+// Deregisters every registered target of the group, load balancers stop sending new
+// requests to them and let in-flight ones finish within the deregistration delay.
+pub async fn deregister_targets(
+    client: &Client,
+    audit: &Audit,
+    target_group_arn: &str,
+) -> AppResult<usize> {
+    let targets: Vec<TargetDescription> = describe_target_health(client, target_group_arn)
+        .await?
+        .into_iter()
+        .filter(|target| target.state != "draining")
+        .map(|target| {
+            TargetDescription::builder()
+                .id(target.id)
+                .set_port(target.port)
+                .build()
+        })
+        .collect();
+    if targets.is_empty() {
+        return Ok(0);
+    }
+
+    let count = targets.len();
+    audit
+        .record(
+            "elasticloadbalancing:DeregisterTargets",
+            &[
+                ("target_group_arn", target_group_arn),
+                ("targets", &count.to_string()),
+            ],
+            client
+                .deregister_targets()
+                .target_group_arn(target_group_arn)
+                .set_targets(Some(targets))
+                .send(),
+        )
+        .await?;
+    Ok(count)
+}
+
+// Waits out the deregistration delay, until no target of the groups is draining.
+pub async fn wait_for_targets_drained(
+    client: &Client,
+    target_group_arns: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    wait_until("Targets draining", timeout, || async move {
+        let mut states = Vec::new();
+        for arn in target_group_arns {
+            let draining = describe_target_health(client, arn)
+                .await?
+                .iter()
+                .filter(|target| target.state == "draining")
+                .count();
+            states.push(if draining == 0 {
+                ResourceState::new(resource_name(arn), "drained", true)
+            } else {
+                ResourceState::new(
+                    resource_name(arn),
+                    format!("{} targets draining", draining),
+                    false,
+                )
+            });
+        }
+        Ok(states)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_elasticloadbalancingv2::operation::describe_listeners::DescribeListenersOutput;
+    use aws_sdk_elasticloadbalancingv2::operation::describe_rules::DescribeRulesOutput;
+    use aws_sdk_elasticloadbalancingv2::operation::describe_target_groups::DescribeTargetGroupsOutput;
+    use aws_sdk_elasticloadbalancingv2::operation::describe_target_health::DescribeTargetHealthOutput;
+    use aws_sdk_elasticloadbalancingv2::types::{
+        Listener as SdkListener, ProtocolEnum, Rule, TargetGroup as SdkTargetGroup,
+        TargetHealth as SdkTargetHealth, TargetHealthDescription, TargetHealthReasonEnum,
+        TargetHealthStateEnum,
+    };
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+
+    const TARGET_GROUP_ARN: &str =
+        "arn:aws:elasticloadbalancing:eu-central-1:123456789012:targetgroup/app-api/1";
+
+    fn forward() -> Action {
+        Action::builder()
+            .r#type(ActionTypeEnum::Forward)
+            .target_group_arn(TARGET_GROUP_ARN)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_describe_inventory() {
+        let listeners = mock!(Client::describe_listeners).then_output(|| {
+            DescribeListenersOutput::builder()
+                .listeners(
+                    SdkListener::builder()
+                        .listener_arn("listener/app/1")
+                        .protocol(ProtocolEnum::Https)
+                        .port(443)
+                        .default_actions(
+                            Action::builder()
+                                .r#type(ActionTypeEnum::FixedResponse)
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+        });
+        let rules = mock!(Client::describe_rules).then_output(|| {
+            DescribeRulesOutput::builder()
+                .rules(
+                    Rule::builder()
+                        .priority("10")
+                        .conditions(
+                            RuleCondition::builder()
+                                .field("path-pattern")
+                                .path_pattern_config(
+                                    PathPatternConditionConfig::builder()
+                                        .values("/api/*")
+                                        .build(),
+                                )
+                                .build(),
+                        )
+                        .actions(forward())
+                        .is_default(false)
+                        .build(),
+                )
+                .rules(Rule::builder().priority("default").is_default(true).build())
+                .build()
+        });
+        let target_groups = mock!(Client::describe_target_groups).then_output(|| {
+            DescribeTargetGroupsOutput::builder()
+                .target_groups(
+                    SdkTargetGroup::builder()
+                        .target_group_name("app-api")
+                        .target_group_arn(TARGET_GROUP_ARN)
+                        .protocol(ProtocolEnum::Http)
+                        .port(8080)
+                        .build(),
+                )
+                .build()
+        });
+        let target_health = mock!(Client::describe_target_health).then_output(|| {
+            DescribeTargetHealthOutput::builder()
+                .target_health_descriptions(
+                    TargetHealthDescription::builder()
+                        .target(
+                            TargetDescription::builder()
+                                .id("10.0.1.10")
+                                .port(8080)
+                                .build(),
+                        )
+                        .target_health(
+                            SdkTargetHealth::builder()
+                                .state(TargetHealthStateEnum::Unhealthy)
+                                .reason(TargetHealthReasonEnum::FailedHealthChecks)
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+        });
+        let client = mock_client!(
+            aws_sdk_elasticloadbalancingv2,
+            RuleMode::Sequential,
+            [&listeners, &rules, &target_groups, &target_health]
+        );
+
+        let load_balancer = LoadBalancer {
+            name: "app-alb".to_owned(),
+            arn: "loadbalancer/app/app-alb/1".to_owned(),
+            dns_name: None,
+        };
+        let inventory = describe_inventory(&client, load_balancer).await.unwrap();
+
+        let routes: Vec<String> = inventory.rules.iter().map(ListenerRule::route).collect();
+        assert_eq!(
+            routes,
+            vec!["HTTPS:443 default", "HTTPS:443 #10 path-pattern=/api/*"]
+        );
+        assert!(inventory.rules[0].target_groups.is_empty());
+        assert_eq!(inventory.rules[1].target_groups, vec![TARGET_GROUP_ARN]);
+        assert_eq!(
+            inventory.target_groups[0].targets,
+            vec![TargetHealth {
+                id: "10.0.1.10".to_owned(),
+                port: Some(8080),
+                state: "unhealthy".to_owned(),
+                reason: Some("Target.FailedHealthChecks".to_owned()),
+                description: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use crate::client::{ClientArgs, SharedConfig, initialize_client, load_shared_config};
+use crate::credentials::{CredentialArgs, initialize_credentials};
+use crate::{AppError, AppResult, ec2};
+use aws_config::Region;
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_sdk_ec2::Client as Ec2Client;
+use clap::Args;
+use futures::stream::{self, StreamExt};
+use log::debug;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+
+#[derive(Args, Debug, Clone)]
+pub struct TargetArgs {
+    #[clap(
+        short,
+        long = "region",
+        alias = "regions",
+        global = true,
+        help = "Repeatable, defaults to eu-central-1"
+    )]
+    pub regions: Vec<String>,
+
+    #[clap(
+        short,
+        long = "profile",
+        alias = "profiles",
+        global = true,
+        help = "Repeatable, defaults to the default profile"
+    )]
+    pub profiles: Vec<String>,
+
+    #[clap(
+        long = "role",
+        global = true,
+        value_name = "ROLE_ARN",
+        conflicts_with = "role_arn",
+        help = "Role ARN to fan out over, assumed on top of every profile. Repeatable"
+    )]
+    pub roles: Vec<String>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Run in every region enabled for the account, the first --region is used to list them"
+    )]
+    pub all_regions: bool,
+
+    #[clap(
+        long,
+        global = true,
+        default_value = "4",
+        help = "Number of targets processed at once"
+    )]
+    pub concurrency: usize,
+}
+
+pub const DEFAULT_REGION: &str = "eu-central-1";
+pub const DEFAULT_PROFILE: &str = "default";
+
+impl TargetArgs {
+    // Regions given on the command line, or the fallback of the command.
+    pub fn regions_or(&self, fallback: &[&str]) -> Vec<String> {
+        or_fallback(&self.regions, fallback)
+    }
+
+    // Profiles given on the command line, or the fallback of the command.
+    pub fn profiles_or(&self, fallback: &[&str]) -> Vec<String> {
+        or_fallback(&self.profiles, fallback)
+    }
+
+    pub fn matrix(&self) -> Vec<Target> {
+        Target::matrix(
+            &self.profiles_or(&[DEFAULT_PROFILE]),
+            &self.roles,
+            &self.regions_or(&[DEFAULT_REGION]),
+        )
+    }
+}
+
+fn or_fallback(values: &[String], fallback: &[&str]) -> Vec<String> {
+    if values.is_empty() {
+        fallback.iter().map(|value| value.to_string()).collect()
+    } else {
+        values.to_vec()
+    }
+}
+
+// A single (profile or role, region) pair an operation runs against.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Target {
+    pub profile: String,
+    pub role_arn: Option<String>,
+    pub region: String,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.role_arn {
+            Some(role_arn) => write!(f, "{}/{}@{}", self.profile, role_arn, self.region),
+            None => write!(f, "{}@{}", self.profile, self.region),
+        }
+    }
+}
+
+impl Target {
+    // Every combination of profile, role and region, in that order of nesting.
+    pub fn matrix(profiles: &[String], roles: &[String], regions: &[String]) -> Vec<Target> {
+        let roles: Vec<Option<String>> = if roles.is_empty() {
+            vec![None]
+        } else {
+            roles.iter().cloned().map(Some).collect()
+        };
+
+        profiles
+            .iter()
+            .flat_map(|profile| {
+                roles.iter().flat_map(move |role_arn| {
+                    regions.iter().map(move |region| Target {
+                        profile: profile.to_owned(),
+                        role_arn: role_arn.clone(),
+                        region: region.to_owned(),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    // Credentials of the target profile, with the target role assumed on top when it has one.
+    pub async fn credentials_provider(
+        &self,
+        credential_args: &CredentialArgs,
+    ) -> SharedCredentialsProvider {
+        let mut credential_args = credential_args.clone();
+        if self.role_arn.is_some() {
+            credential_args.role_arn = self.role_arn.clone();
+        }
+        let region = Region::new(self.region.clone());
+        initialize_credentials(&region, &self.profile, &credential_args).await
+    }
+}
+
+#[derive(Debug)]
+pub struct TargetResult<T> {
+    pub target: Target,
+    pub result: AppResult<T>,
+}
+
+type CredentialsKey = (String, Option<String>);
+
+pub struct Fanout {
+    targets: Vec<Target>,
+    providers: HashMap<CredentialsKey, SharedCredentialsProvider>,
+    client_args: ClientArgs,
+    concurrency: usize,
+}
+
+impl Fanout {
+    pub async fn new(
+        args: &TargetArgs,
+        credential_args: &CredentialArgs,
+        client_args: &ClientArgs,
+    ) -> AppResult<Self> {
+        let mut fanout = Self::with_targets(
+            args.matrix(),
+            credential_args,
+            client_args,
+            args.concurrency,
+        )
+        .await;
+
+        if args.all_regions {
+            let first = fanout.targets.first().ok_or("No profile or region given")?;
+            let config = fanout.shared_config(first).await?;
+            let regions = ec2::list_regions(&initialize_client::<Ec2Client>(&config)).await?;
+            debug!("Enabled regions: {:?}", regions);
+            let profiles = args.profiles_or(&[DEFAULT_PROFILE]);
+            fanout.targets = Target::matrix(&profiles, &args.roles, &regions);
+        }
+
+        Ok(fanout)
+    }
+
+    // Credentials are resolved once per profile and role, before anything runs concurrently,
+    // so MFA prompts do not interleave and every region reuses the same session.
+    pub async fn with_targets(
+        targets: Vec<Target>,
+        credential_args: &CredentialArgs,
+        client_args: &ClientArgs,
+        concurrency: usize,
+    ) -> Self {
+        let mut providers = HashMap::new();
+        for target in &targets {
+            let key = (target.profile.clone(), target.role_arn.clone());
+            if providers.contains_key(&key) {
+                continue;
+            }
+            providers.insert(key, target.credentials_provider(credential_args).await);
+        }
+
+        Self {
+            targets,
+            providers,
+            client_args: client_args.clone(),
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    pub fn targets(&self) -> &[Target] {
+        &self.targets
+    }
+
+    pub async fn shared_config(&self, target: &Target) -> AppResult<SharedConfig> {
+        let provider = self
+            .providers
+            .get(&(target.profile.clone(), target.role_arn.clone()))
+            .ok_or_else(|| format!("No credentials for {}", target))?;
+        load_shared_config(
+            Region::new(target.region.clone()),
+            provider,
+            &self.client_args,
+        )
+        .await
+    }
+
+    // Runs the operation for every target with bounded concurrency, results keep target order.
+    pub async fn run<T, F, Fut>(&self, operation: F) -> Vec<TargetResult<T>>
+    where
+        F: Fn(Target, SharedConfig) -> Fut,
+        Fut: Future<Output = AppResult<T>>,
+    {
+        stream::iter(self.targets.iter().cloned())
+            .map(|target| {
+                let operation = &operation;
+                async move {
+                    let result = match self.shared_config(&target).await {
+                        Ok(config) => operation(target.clone(), config).await,
+                        Err(err) => Err(err),
+                    };
+                    TargetResult { target, result }
+                }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+}
+
+// Prints every failed target and turns any failure into an error once all targets finished.
+pub fn report_errors<T>(results: Vec<TargetResult<T>>) -> AppResult<Vec<(Target, T)>> {
+    let mut succeeded = Vec::new();
+    let mut failed: Vec<(Target, AppError)> = Vec::new();
+
+    for TargetResult { target, result } in results {
+        match result {
+            Ok(value) => succeeded.push((target, value)),
+            Err(err) => failed.push((target, err)),
+        }
+    }
+
+    for (target, err) in &failed {
+        eprintln!("[{}] failed: {}", target, err);
+    }
+
+    if failed.is_empty() {
+        Ok(succeeded)
+    } else {
+        Err(format!(
+            "{} of {} targets failed",
+            failed.len(),
+            failed.len() + succeeded.len()
+        )
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_matrix() {
+        let profiles = vec!["dev".to_owned(), "prod".to_owned()];
+        let regions = vec!["eu-central-1".to_owned(), "us-east-2".to_owned()];
+
+        let targets = Target::matrix(&profiles, &[], &regions);
+        assert_eq!(targets.len(), 4);
+        assert_eq!(targets[1].to_string(), "dev@us-east-2");
+        assert_eq!(targets[2].to_string(), "prod@eu-central-1");
+
+        let roles = vec!["arn:aws:iam::123456789012:role/ops".to_owned()];
+        let targets = Target::matrix(&profiles[..1], &roles, &regions[..1]);
+        assert_eq!(
+            targets[0].to_string(),
+            "dev/arn:aws:iam::123456789012:role/ops@eu-central-1"
+        );
+    }
+}
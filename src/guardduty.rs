@@ -1,7 +1,25 @@
 use crate::AppResult;
+use crate::output::Tabular;
 use aws_sdk_guardduty::Client;
 use aws_sdk_guardduty::types::DetectorFeatureResult;
-use log::debug;
+use log::{debug, info};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProtectedBucket {
+    pub name: String,
+    pub plan_id: String,
+}
+
+impl Tabular for ProtectedBucket {
+    fn headers() -> Vec<&'static str> {
+        vec!["BUCKET", "PROTECTION PLAN"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.name.clone(), self.plan_id.clone()]
+    }
+}
 
 pub async fn get_buckets_with_guardduty_enabled(client: &Client) -> AppResult<bool> {
     let detectors = client.list_detectors().send().await?;
@@ -20,12 +38,12 @@ pub async fn get_buckets_with_guardduty_enabled(client: &Client) -> AppResult<bo
         .send()
         .await?;
 
-    println!("GuardDuty status: {:?}", &detector.status());
+    info!("GuardDuty status: {:?}", &detector.status());
 
     for feature in detector.features() {
         let name = feature.name();
         if matches!(name, Some(DetectorFeatureResult::S3DataEvents)) {
-            println!("S3 protection status: {:?}", feature.status());
+            info!("S3 protection status: {:?}", feature.status());
         } else {
             return Ok(true);
         }
@@ -38,9 +56,9 @@ pub async fn list_malware_protected_buckets(
     client: &Client,
     include: &[String],
     exclude: &[String],
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<ProtectedBucket>> {
     let mut next_token = None;
-    let mut bucket_names = Vec::new();
+    let mut buckets = Vec::new();
 
     let include = include.to_vec();
     let exclude = exclude.to_vec();
@@ -56,12 +74,13 @@ pub async fn list_malware_protected_buckets(
             .send()
             .await?;
 
-        for plan_id in resp.malware_protection_plans() {
+        for plan_summary in resp.malware_protection_plans() {
+            let plan_id = plan_summary
+                .malware_protection_plan_id()
+                .unwrap_or_default();
             let plan = client
                 .get_malware_protection_plan()
-                .malware_protection_plan_id(
-                    plan_id.malware_protection_plan_id().unwrap_or_default(),
-                )
+                .malware_protection_plan_id(plan_id)
                 .send()
                 .await?;
 
@@ -70,7 +89,10 @@ pub async fn list_malware_protected_buckets(
                 && let Some(name) = s3_bucket.bucket_name()
                 && filter(name)
             {
-                bucket_names.push(name.to_owned());
+                buckets.push(ProtectedBucket {
+                    name: name.to_owned(),
+                    plan_id: plan_id.to_owned(),
+                });
             }
         }
 
@@ -80,7 +102,7 @@ pub async fn list_malware_protected_buckets(
         }
     }
 
-    bucket_names.sort();
-    bucket_names.dedup();
-    Ok(bucket_names)
+    buckets.sort();
+    buckets.dedup_by(|a, b| a.name == b.name);
+    Ok(buckets)
 }
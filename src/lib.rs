@@ -1,18 +1,29 @@
+pub mod appautoscaling;
+pub mod audit;
 pub mod autoscaling;
+pub mod cli;
 pub mod client;
 pub mod codecommit;
 pub mod codepipeline;
+pub mod config;
+pub mod credentials;
 pub mod ec2;
 pub mod ecs;
 pub mod elasticache;
 pub mod elbv2;
+pub mod fanout;
 pub mod guardduty;
+pub mod lock;
+pub mod output;
 pub mod rds;
+pub mod retry;
 pub mod s3;
 pub mod sesv2;
+pub mod sweeper;
 mod utils;
+pub mod wait;
 
 mod errors;
 
 pub use self::errors::{AppError, AppResult};
-pub(crate) use utils::sanitize_string;
+pub(crate) use utils::{final_snapshot_name, sanitize_string};
@@ -0,0 +1,290 @@
+use crate::AppResult;
+use crate::fanout::Target;
+use aws_sdk_dynamodb::Client;
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::Utc;
+use clap::Args;
+use log::{debug, error, warn};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct LockArgs {
+    #[clap(
+        long,
+        global = true,
+        env = "AWS_TOOLKIT_LOCK_TABLE",
+        help = "DynamoDB table holding run locks, destructive commands run unlocked without it"
+    )]
+    pub lock_table: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        default_value = "300",
+        value_name = "SECONDS",
+        help = "Lease of the run lock, renewed while the command runs"
+    )]
+    pub lock_lease: u64,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Take the run lock even if another run holds it"
+    )]
+    pub force_unlock: bool,
+}
+
+// Partition key of the lock table, locks and runs share it with a `lock#` and `run#` prefix.
+const KEY: &str = "lock_id";
+
+// What is locked: the named environment, or the exact set of targets without one.
+pub fn lock_name(environment: Option<&str>, targets: &[Target]) -> String {
+    match environment {
+        Some(environment) => format!("env:{}", environment),
+        None => {
+            let mut targets: Vec<String> = targets.iter().map(ToString::to_string).collect();
+            targets.sort();
+            targets.dedup();
+            format!("targets:{}", targets.join(","))
+        }
+    }
+}
+
+// Local user and host, recorded as the lock owner.
+pub fn whoami() -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_owned());
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_owned())
+        .filter(|host| !host.is_empty());
+    match host {
+        Some(host) => format!("{}@{}", user, host),
+        None => user,
+    }
+}
+
+fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+fn string(value: impl Into<String>) -> AttributeValue {
+    AttributeValue::S(value.into())
+}
+
+fn number(value: i64) -> AttributeValue {
+    AttributeValue::N(value.to_string())
+}
+
+fn attribute<'a>(item: &'a HashMap<String, AttributeValue>, name: &str) -> &'a str {
+    match item.get(name) {
+        Some(AttributeValue::S(value)) | Some(AttributeValue::N(value)) => value,
+        _ => "?",
+    }
+}
+
+// A held lock, renewed in the background until released.
+pub struct RunLock {
+    client: Client,
+    table: String,
+    lock_id: String,
+    run_id: String,
+    heartbeat: JoinHandle<()>,
+}
+
+impl RunLock {
+    // Takes the lock with a conditional write, which only succeeds when it is free or its
+    // lease ran out, and registers the run.
+    pub async fn acquire(
+        client: Client,
+        args: &LockArgs,
+        table: &str,
+        name: &str,
+        command: &str,
+    ) -> AppResult<Self> {
+        let lock_id = format!("lock#{}", name);
+        let run_id = format!(
+            "{}-{:08x}",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            fastrand::u32(..)
+        );
+        let owner = whoami();
+        let lease = Duration::from_secs(args.lock_lease.max(30));
+        let started_at = now();
+
+        let mut put = client
+            .put_item()
+            .table_name(table)
+            .item(KEY, string(&lock_id))
+            .item("run_id", string(&run_id))
+            .item("owner", string(&owner))
+            .item("command", string(command))
+            .item("acquired_at", number(started_at))
+            .item("expires_at", number(started_at + lease.as_secs() as i64));
+        if !args.force_unlock {
+            put = put
+                .condition_expression("attribute_not_exists(#key) OR expires_at < :now")
+                .expression_attribute_names("#key", KEY)
+                .expression_attribute_values(":now", number(started_at));
+        }
+
+        if let Err(err) = put.send().await {
+            if err
+                .as_service_error()
+                .is_some_and(|e| e.is_conditional_check_failed_exception())
+            {
+                return Err(Self::held_by(&client, table, &lock_id, name).await.into());
+            }
+            return Err(err.into());
+        }
+        if args.force_unlock {
+            warn!("Lock {} taken by force", name);
+        }
+        debug!("Acquired lock {} as run {}", name, run_id);
+
+        client
+            .put_item()
+            .table_name(table)
+            .item(KEY, string(format!("run#{}", run_id)))
+            .item("lock", string(name))
+            .item("owner", string(&owner))
+            .item("command", string(command))
+            .item("started_at", number(started_at))
+            .item("result", string("running"))
+            .send()
+            .await?;
+
+        let heartbeat = tokio::spawn(Self::heartbeat(
+            client.clone(),
+            table.to_owned(),
+            lock_id.clone(),
+            run_id.clone(),
+            lease,
+        ));
+
+        Ok(Self {
+            client,
+            table: table.to_owned(),
+            lock_id,
+            run_id,
+            heartbeat,
+        })
+    }
+
+    async fn held_by(client: &Client, table: &str, lock_id: &str, name: &str) -> String {
+        let holder = client
+            .get_item()
+            .table_name(table)
+            .key(KEY, string(lock_id))
+            .consistent_read(true)
+            .send()
+            .await
+            .ok()
+            .and_then(|output| output.item);
+        match holder {
+            Some(item) => format!(
+                "{} is locked by {} running {} (run {}), the lease expires at {}. Use --force-unlock to take it over",
+                name,
+                attribute(&item, "owner"),
+                attribute(&item, "command"),
+                attribute(&item, "run_id"),
+                attribute(&item, "expires_at"),
+            ),
+            None => format!("{} is locked by another run, try again", name),
+        }
+    }
+
+    // Extends the lease every third of it, only while this run still owns the lock.
+    async fn heartbeat(
+        client: Client,
+        table: String,
+        lock_id: String,
+        run_id: String,
+        lease: Duration,
+    ) {
+        let mut interval = tokio::time::interval(lease / 3);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let renewed = client
+                .update_item()
+                .table_name(&table)
+                .key(KEY, string(&lock_id))
+                .update_expression("SET expires_at = :expires_at")
+                .condition_expression("run_id = :run_id")
+                .expression_attribute_values(":expires_at", number(now() + lease.as_secs() as i64))
+                .expression_attribute_values(":run_id", string(&run_id))
+                .send()
+                .await;
+            match renewed {
+                Ok(_) => debug!("Renewed lock {}", lock_id),
+                Err(err) => error!("Cannot renew lock {}: {}", lock_id, err),
+            }
+        }
+    }
+
+    // Records the result of the run and frees the lock.
+    pub async fn release<T>(self, result: &AppResult<T>) -> AppResult<()> {
+        self.heartbeat.abort();
+
+        let outcome = match result {
+            Ok(_) => "success".to_owned(),
+            Err(err) => format!("failure: {}", err),
+        };
+        self.client
+            .update_item()
+            .table_name(&self.table)
+            .key(KEY, string(format!("run#{}", self.run_id)))
+            .update_expression("SET finished_at = :finished_at, #result = :result")
+            .expression_attribute_names("#result", "result")
+            .expression_attribute_values(":finished_at", number(now()))
+            .expression_attribute_values(":result", string(outcome))
+            .send()
+            .await?;
+
+        // A run whose lock was forced away must not free the lock of the new holder.
+        let deleted = self
+            .client
+            .delete_item()
+            .table_name(&self.table)
+            .key(KEY, string(&self.lock_id))
+            .condition_expression("run_id = :run_id")
+            .expression_attribute_values(":run_id", string(&self.run_id))
+            .send()
+            .await;
+        if let Err(err) = deleted {
+            if err
+                .as_service_error()
+                .is_some_and(|e| e.is_conditional_check_failed_exception())
+            {
+                warn!("Lock {} was taken over by another run", self.lock_id);
+            } else {
+                return Err(err.into());
+            }
+        }
+        debug!("Released lock {}", self.lock_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_name() {
+        let profiles = vec!["prod".to_owned()];
+        let regions = vec!["us-east-2".to_owned(), "eu-central-1".to_owned()];
+        let targets = Target::matrix(&profiles, &[], &regions);
+
+        assert_eq!(lock_name(Some("prod"), &targets), "env:prod");
+        assert_eq!(
+            lock_name(None, &targets),
+            "targets:prod@eu-central-1,prod@us-east-2"
+        );
+    }
+}
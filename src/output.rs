@@ -0,0 +1,178 @@
+use crate::AppResult;
+use crate::fanout::Target;
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    Table,
+}
+
+// Result types which can be shown as rows of text or of an aligned table.
+pub trait Tabular {
+    fn headers() -> Vec<&'static str>;
+    fn row(&self) -> Vec<String>;
+}
+
+// A result together with the profile and region it was produced for.
+#[derive(Serialize, Debug, Clone)]
+pub struct TargetRecord<T> {
+    pub profile: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role_arn: Option<String>,
+    pub region: String,
+    #[serde(flatten)]
+    pub item: T,
+}
+
+impl<T> TargetRecord<T> {
+    pub fn new(target: &Target, item: T) -> Self {
+        Self {
+            profile: target.profile.clone(),
+            role_arn: target.role_arn.clone(),
+            region: target.region.clone(),
+            item,
+        }
+    }
+}
+
+impl<T: Tabular> Tabular for TargetRecord<T> {
+    fn headers() -> Vec<&'static str> {
+        let mut headers = vec!["PROFILE", "REGION"];
+        headers.extend(T::headers());
+        headers
+    }
+
+    fn row(&self) -> Vec<String> {
+        let profile = match &self.role_arn {
+            Some(role_arn) => format!("{}/{}", self.profile, role_arn),
+            None => self.profile.clone(),
+        };
+        let mut row = vec![profile, self.region.clone()];
+        row.extend(self.item.row());
+        row
+    }
+}
+
+// Tags every item with its target, keeping the target order of the fan-out.
+pub fn with_targets<T>(results: Vec<(Target, Vec<T>)>) -> Vec<TargetRecord<T>> {
+    results
+        .into_iter()
+        .flat_map(|(target, items)| {
+            items
+                .into_iter()
+                .map(move |item| TargetRecord::new(&target, item))
+        })
+        .collect()
+}
+
+fn render_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn render<T: Serialize + Tabular>(format: OutputFormat, records: &[T]) -> AppResult<String> {
+    Ok(match format {
+        OutputFormat::Text => records
+            .iter()
+            .map(|record| record.row().join("\t"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Table => {
+            let mut rows = vec![T::headers().into_iter().map(str::to_owned).collect()];
+            rows.extend(records.iter().map(Tabular::row));
+            render_table(&rows)
+        }
+        OutputFormat::Json => serde_json::to_string_pretty(records)?,
+        OutputFormat::Yaml => serde_yaml::to_string(records)?.trim_end().to_owned(),
+    })
+}
+
+pub fn print<T: Serialize + Tabular>(format: OutputFormat, records: &[T]) -> AppResult<()> {
+    let rendered = render(format, records)?;
+    if !rendered.is_empty() {
+        println!("{}", rendered);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Service {
+        name: String,
+        desired_count: i32,
+    }
+
+    impl Tabular for Service {
+        fn headers() -> Vec<&'static str> {
+            vec!["NAME", "DESIRED"]
+        }
+
+        fn row(&self) -> Vec<String> {
+            vec![self.name.clone(), self.desired_count.to_string()]
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let target = Target {
+            profile: "prod".to_owned(),
+            role_arn: None,
+            region: "eu-central-1".to_owned(),
+        };
+        let records = with_targets(vec![(
+            target,
+            vec![Service {
+                name: "auth".to_owned(),
+                desired_count: 2,
+            }],
+        )]);
+
+        assert_eq!(
+            render(OutputFormat::Text, &records).unwrap(),
+            "prod\teu-central-1\tauth\t2"
+        );
+        assert_eq!(
+            render(OutputFormat::Table, &records).unwrap(),
+            "PROFILE  REGION        NAME  DESIRED\nprod     eu-central-1  auth  2"
+        );
+        assert_eq!(
+            render(OutputFormat::Json, &records).unwrap(),
+            serde_json::to_string_pretty(&serde_json::json!([{
+                "profile": "prod",
+                "region": "eu-central-1",
+                "name": "auth",
+                "desired_count": 2
+            }]))
+            .unwrap()
+        );
+    }
+}
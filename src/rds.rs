@@ -1,20 +1,78 @@
-use crate::{AppResult, sanitize_string};
-use aws_sdk_rds::{Client, types::Filter};
-use chrono::{self, Utc};
+use crate::audit::Audit;
+use crate::output::Tabular;
+use crate::wait::{ResourceState, wait_until};
+use crate::{AppResult, final_snapshot_name};
+use aws_sdk_rds::Client;
+use aws_sdk_rds::types::{Filter, Tag};
+use chrono::{DateTime, Utc};
 use log::debug;
+use serde::Serialize;
+use std::time::Duration;
 
-pub async fn list_db_instances(client: &Client, cluster: &str) -> AppResult<Vec<String>> {
-    let mut db_instances = Vec::new();
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DbInstance {
+    pub id: String,
+    pub status: String,
+    pub engine: Option<String>,
+    pub instance_class: Option<String>,
+}
 
-    let filter_value = format!("{}-postgres", cluster);
-    let filter = Filter::builder()
-        .name("db-instance-id") // same as Name field in boto3
-        .values(filter_value)
-        .build();
+// An Aurora cluster, stopped and deleted as a whole.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DbCluster {
+    pub id: String,
+    pub status: String,
+    pub engine: Option<String>,
+    pub members: Vec<String>,
+}
+
+impl Tabular for DbCluster {
+    fn headers() -> Vec<&'static str> {
+        vec!["DB CLUSTER", "STATUS", "ENGINE", "MEMBERS"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.status.clone(),
+            self.engine.clone().unwrap_or_default(),
+            self.members.join(","),
+        ]
+    }
+}
+
+impl Tabular for DbInstance {
+    fn headers() -> Vec<&'static str> {
+        vec!["DB INSTANCE", "STATUS", "ENGINE", "CLASS"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.status.clone(),
+            self.engine.clone().unwrap_or_default(),
+            self.instance_class.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+// Engines of standalone instances, Aurora instances are handled through their cluster.
+const INSTANCE_ENGINES: &[&str] = &["postgres", "mysql", "mariadb"];
+const CLUSTER_ENGINES: &[&str] = &["aurora-postgresql", "aurora-mysql"];
+
+fn engine_filter(engines: &[&str]) -> Filter {
+    Filter::builder()
+        .name("engine")
+        .set_values(Some(engines.iter().map(|e| e.to_string()).collect()))
+        .build()
+}
+
+pub async fn list_db_instances(client: &Client, cluster: &str) -> AppResult<Vec<DbInstance>> {
+    let mut db_instances = Vec::new();
 
     let mut db_instances_stream = client
         .describe_db_instances()
-        .filters(filter)
+        .filters(engine_filter(INSTANCE_ENGINES))
         .max_records(100)
         .into_paginator()
         .send();
@@ -29,15 +87,21 @@ pub async fn list_db_instances(client: &Client, cluster: &str) -> AppResult<Vec<
                 .filter_map(|instance| {
                     let id = instance.db_instance_identifier()?;
 
-                    if !id.contains(cluster) {
+                    if !id.contains(cluster) || instance.db_cluster_identifier().is_some() {
                         return None;
                     }
 
-                    if !["available", "stopped"].contains(&instance.db_instance_status()?) {
+                    let status = instance.db_instance_status()?;
+                    if !["available", "stopped"].contains(&status) {
                         return None;
                     };
 
-                    Some(id.to_owned())
+                    Some(DbInstance {
+                        id: id.to_owned(),
+                        status: status.to_owned(),
+                        engine: instance.engine().map(ToOwned::to_owned),
+                        instance_class: instance.db_instance_class().map(ToOwned::to_owned),
+                    })
                 }),
         );
     }
@@ -45,53 +109,1320 @@ pub async fn list_db_instances(client: &Client, cluster: &str) -> AppResult<Vec<
     Ok(db_instances)
 }
 
-pub async fn disable_deletion_protection(client: &Client, db_instance_id: &str) -> AppResult<()> {
-    client
-        .modify_db_instance()
-        .db_instance_identifier(db_instance_id)
-        .set_deletion_protection(Some(false))
-        .apply_immediately(true)
-        .send()
+pub async fn list_db_clusters(client: &Client, cluster: &str) -> AppResult<Vec<DbCluster>> {
+    let mut db_clusters = Vec::new();
+
+    let mut db_clusters_stream = client
+        .describe_db_clusters()
+        .filters(engine_filter(CLUSTER_ENGINES))
+        .max_records(100)
+        .into_paginator()
+        .send();
+
+    while let Some(db_clusters_output) = db_clusters_stream.next().await {
+        debug!("DB Clusters: {:?}", db_clusters_output);
+
+        db_clusters.extend(
+            db_clusters_output?
+                .db_clusters()
+                .iter()
+                .filter_map(|db_cluster| {
+                    let id = db_cluster.db_cluster_identifier()?;
+                    if !id.contains(cluster) {
+                        return None;
+                    }
+
+                    let status = db_cluster.status()?;
+                    if !["available", "stopped"].contains(&status) {
+                        return None;
+                    }
+
+                    Some(DbCluster {
+                        id: id.to_owned(),
+                        status: status.to_owned(),
+                        engine: db_cluster.engine().map(ToOwned::to_owned),
+                        members: db_cluster
+                            .db_cluster_members()
+                            .iter()
+                            .filter_map(|member| member.db_instance_identifier())
+                            .map(ToOwned::to_owned)
+                            .collect(),
+                    })
+                }),
+        );
+    }
+
+    Ok(db_clusters)
+}
+
+pub async fn disable_deletion_protection(
+    client: &Client,
+    audit: &Audit,
+    db_instance_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:ModifyDBInstance",
+            &[
+                ("db_instance_identifier", db_instance_id),
+                ("deletion_protection", "false"),
+            ],
+            client
+                .modify_db_instance()
+                .db_instance_identifier(db_instance_id)
+                .set_deletion_protection(Some(false))
+                .apply_immediately(true)
+                .send(),
+        )
         .await?;
     Ok(())
 }
 
 pub async fn delete_db_instance_skip_final_snapshot(
     client: &Client,
+    audit: &Audit,
     db_instance_id: &str,
 ) -> AppResult<()> {
-    client
-        .delete_db_instance()
-        .db_instance_identifier(db_instance_id)
-        .skip_final_snapshot(true)
-        .send()
+    audit
+        .record(
+            "rds:DeleteDBInstance",
+            &[
+                ("db_instance_identifier", db_instance_id),
+                ("skip_final_snapshot", "true"),
+            ],
+            client
+                .delete_db_instance()
+                .db_instance_identifier(db_instance_id)
+                .skip_final_snapshot(true)
+                .send(),
+        )
         .await?;
     Ok(())
 }
 
 pub async fn delete_db_instance_with_final_snapshot(
     client: &Client,
+    audit: &Audit,
     db_instance_id: &str,
 ) -> AppResult<()> {
-    let now = Utc::now();
-    let now_formatted = now.format("%Y-%m-%d-%H-%M");
-    let final_snapshot_identifier =
-        sanitize_string(&format!("{}-{}", db_instance_id, now_formatted));
-    client
-        .delete_db_instance()
+    let final_snapshot_identifier = final_snapshot_name(db_instance_id);
+    audit
+        .record(
+            "rds:DeleteDBInstance",
+            &[
+                ("db_instance_identifier", db_instance_id),
+                ("final_db_snapshot_identifier", &final_snapshot_identifier),
+            ],
+            client
+                .delete_db_instance()
+                .db_instance_identifier(db_instance_id)
+                .skip_final_snapshot(false)
+                .final_db_snapshot_identifier(&final_snapshot_identifier)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn stop_db_instance(
+    client: &Client,
+    audit: &Audit,
+    db_instance_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:StopDBInstance",
+            &[("db_instance_identifier", db_instance_id)],
+            client
+                .stop_db_instance()
+                .db_instance_identifier(db_instance_id)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn stop_db_cluster(client: &Client, audit: &Audit, db_cluster_id: &str) -> AppResult<()> {
+    audit
+        .record(
+            "rds:StopDBCluster",
+            &[("db_cluster_identifier", db_cluster_id)],
+            client
+                .stop_db_cluster()
+                .db_cluster_identifier(db_cluster_id)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn start_db_cluster(
+    client: &Client,
+    audit: &Audit,
+    db_cluster_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:StartDBCluster",
+            &[("db_cluster_identifier", db_cluster_id)],
+            client
+                .start_db_cluster()
+                .db_cluster_identifier(db_cluster_id)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn disable_cluster_deletion_protection(
+    client: &Client,
+    audit: &Audit,
+    db_cluster_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:ModifyDBCluster",
+            &[
+                ("db_cluster_identifier", db_cluster_id),
+                ("deletion_protection", "false"),
+            ],
+            client
+                .modify_db_cluster()
+                .db_cluster_identifier(db_cluster_id)
+                .deletion_protection(false)
+                .apply_immediately(true)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Aurora instances have no snapshots of their own, the cluster snapshot covers them.
+pub async fn delete_db_cluster_member(
+    client: &Client,
+    audit: &Audit,
+    db_instance_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:DeleteDBInstance",
+            &[("db_instance_identifier", db_instance_id)],
+            client
+                .delete_db_instance()
+                .db_instance_identifier(db_instance_id)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// A cluster can only be deleted once its members are gone.
+pub async fn delete_db_cluster_skip_final_snapshot(
+    client: &Client,
+    audit: &Audit,
+    db_cluster_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:DeleteDBCluster",
+            &[
+                ("db_cluster_identifier", db_cluster_id),
+                ("skip_final_snapshot", "true"),
+            ],
+            client
+                .delete_db_cluster()
+                .db_cluster_identifier(db_cluster_id)
+                .skip_final_snapshot(true)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_db_cluster_with_final_snapshot(
+    client: &Client,
+    audit: &Audit,
+    db_cluster_id: &str,
+) -> AppResult<()> {
+    let final_snapshot_identifier = final_snapshot_name(db_cluster_id);
+    audit
+        .record(
+            "rds:DeleteDBCluster",
+            &[
+                ("db_cluster_identifier", db_cluster_id),
+                ("final_db_snapshot_identifier", &final_snapshot_identifier),
+            ],
+            client
+                .delete_db_cluster()
+                .db_cluster_identifier(db_cluster_id)
+                .skip_final_snapshot(false)
+                .final_db_snapshot_identifier(&final_snapshot_identifier)
+                .send(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Current status of the instances, instances which no longer exist are reported as `deleted`.
+async fn describe_db_instance_states(
+    client: &Client,
+    db_instance_ids: &[String],
+    expected: &str,
+) -> AppResult<Vec<ResourceState>> {
+    let filter = Filter::builder()
+        .name("db-instance-id")
+        .set_values(Some(db_instance_ids.to_vec()))
+        .build();
+    let output = client
+        .describe_db_instances()
+        .filters(filter)
+        .send()
+        .await?;
+
+    Ok(db_instance_ids
+        .iter()
+        .map(|id| {
+            let status = output
+                .db_instances()
+                .iter()
+                .find(|instance| instance.db_instance_identifier() == Some(id.as_str()))
+                .map(|instance| instance.db_instance_status().unwrap_or("unknown"))
+                .unwrap_or("deleted");
+            ResourceState::new(id, status, status == expected)
+        })
+        .collect())
+}
+
+pub async fn wait_for_db_instances_stopped(
+    client: &Client,
+    db_instance_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    if db_instance_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    wait_until("RDS instances stopping", timeout, || {
+        describe_db_instance_states(client, db_instance_ids, "stopped")
+    })
+    .await
+}
+
+// Deletion with a final snapshot takes as long as the snapshot, so the timeout should allow it.
+pub async fn wait_for_db_instances_deleted(
+    client: &Client,
+    db_instance_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    if db_instance_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    wait_until("RDS instances deleting", timeout, || {
+        describe_db_instance_states(client, db_instance_ids, "deleted")
+    })
+    .await
+}
+
+// Current status of the clusters, clusters which no longer exist are reported as `deleted`.
+async fn describe_db_cluster_states(
+    client: &Client,
+    db_cluster_ids: &[String],
+    expected: &str,
+) -> AppResult<Vec<ResourceState>> {
+    let filter = Filter::builder()
+        .name("db-cluster-id")
+        .set_values(Some(db_cluster_ids.to_vec()))
+        .build();
+    let output = client.describe_db_clusters().filters(filter).send().await?;
+
+    Ok(db_cluster_ids
+        .iter()
+        .map(|id| {
+            let status = output
+                .db_clusters()
+                .iter()
+                .find(|db_cluster| db_cluster.db_cluster_identifier() == Some(id.as_str()))
+                .map(|db_cluster| db_cluster.status().unwrap_or("unknown"))
+                .unwrap_or("deleted");
+            ResourceState::new(id, status, status == expected)
+        })
+        .collect())
+}
+
+pub async fn wait_for_db_clusters_stopped(
+    client: &Client,
+    db_cluster_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    if db_cluster_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    wait_until("Aurora clusters stopping", timeout, || {
+        describe_db_cluster_states(client, db_cluster_ids, "stopped")
+    })
+    .await
+}
+
+pub async fn wait_for_db_clusters_deleted(
+    client: &Client,
+    db_cluster_ids: &[String],
+    timeout: Duration,
+) -> AppResult<Vec<ResourceState>> {
+    if db_cluster_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    wait_until("Aurora clusters deleting", timeout, || {
+        describe_db_cluster_states(client, db_cluster_ids, "deleted")
+    })
+    .await
+}
+
+// Tags of a final snapshot holding the configuration its instance is restored with.
+const TAG_PREFIX: &str = "aws-toolkit:";
+
+// An instance deleted with a final snapshot, with what the snapshot itself does not keep.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RestorableInstance {
+    pub id: String,
+    pub snapshot: String,
+    pub instance_class: String,
+    pub subnet_group: Option<String>,
+    pub parameter_group: Option<String>,
+    pub option_group: Option<String>,
+    pub security_groups: Vec<String>,
+    pub multi_az: bool,
+}
+
+impl Tabular for RestorableInstance {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "DB INSTANCE",
+            "SNAPSHOT",
+            "CLASS",
+            "SUBNET GROUP",
+            "MULTI AZ",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.snapshot.clone(),
+            self.instance_class.clone(),
+            self.subnet_group.clone().unwrap_or_default(),
+            self.multi_az.to_string(),
+        ]
+    }
+}
+
+impl RestorableInstance {
+    fn to_tags(&self) -> Vec<Tag> {
+        let mut values = vec![
+            ("db-instance", Some(self.id.clone())),
+            ("instance-class", Some(self.instance_class.clone())),
+            ("subnet-group", self.subnet_group.clone()),
+            ("parameter-group", self.parameter_group.clone()),
+            ("option-group", self.option_group.clone()),
+            ("multi-az", Some(self.multi_az.to_string())),
+        ];
+        if !self.security_groups.is_empty() {
+            values.push(("security-groups", Some(self.security_groups.join(" "))));
+        }
+        values
+            .into_iter()
+            .filter_map(|(key, value)| {
+                Some(
+                    Tag::builder()
+                        .key(format!("{}{}", TAG_PREFIX, key))
+                        .value(value?)
+                        .build(),
+                )
+            })
+            .collect()
+    }
+
+    // None for snapshots which were not taken by scale-in-ecs.
+    fn from_tags(snapshot: &str, tags: &[Tag]) -> Option<Self> {
+        let tag = |key: &str| {
+            tags.iter()
+                .find(|tag| tag.key() == Some(&format!("{}{}", TAG_PREFIX, key)))
+                .and_then(|tag| tag.value())
+                .map(ToOwned::to_owned)
+        };
+        Some(Self {
+            id: tag("db-instance")?,
+            snapshot: snapshot.to_owned(),
+            instance_class: tag("instance-class")?,
+            subnet_group: tag("subnet-group"),
+            parameter_group: tag("parameter-group"),
+            option_group: tag("option-group"),
+            security_groups: tag("security-groups")
+                .map(|groups| groups.split_whitespace().map(ToOwned::to_owned).collect())
+                .unwrap_or_default(),
+            multi_az: tag("multi-az").as_deref() == Some("true"),
+        })
+    }
+}
+
+// Reads the configuration of a live instance and names its final snapshot.
+pub async fn describe_restorable_instance(
+    client: &Client,
+    db_instance_id: &str,
+) -> AppResult<RestorableInstance> {
+    let output = client
+        .describe_db_instances()
         .db_instance_identifier(db_instance_id)
-        .skip_final_snapshot(false)
-        .final_db_snapshot_identifier(final_snapshot_identifier)
         .send()
         .await?;
+    let instance = output
+        .db_instances()
+        .first()
+        .ok_or_else(|| format!("DB instance {} not found", db_instance_id))?;
+
+    Ok(RestorableInstance {
+        id: db_instance_id.to_owned(),
+        snapshot: final_snapshot_name(db_instance_id),
+        instance_class: instance
+            .db_instance_class()
+            .ok_or("Unknown instance class")?
+            .to_owned(),
+        subnet_group: instance
+            .db_subnet_group()
+            .and_then(|group| group.db_subnet_group_name())
+            .map(ToOwned::to_owned),
+        parameter_group: instance
+            .db_parameter_groups()
+            .first()
+            .and_then(|group| group.db_parameter_group_name())
+            .map(ToOwned::to_owned),
+        option_group: instance
+            .option_group_memberships()
+            .first()
+            .and_then(|group| group.option_group_name())
+            .map(ToOwned::to_owned),
+        security_groups: instance
+            .vpc_security_groups()
+            .iter()
+            .filter_map(|group| group.vpc_security_group_id())
+            .map(ToOwned::to_owned)
+            .collect(),
+        multi_az: instance.multi_az().unwrap_or_default(),
+    })
+}
+
+// Deletes the instance with a final snapshot and tags the snapshot with the configuration
+// once it shows up, restore_db_instance recreates the instance from it.
+pub async fn delete_db_instance_for_restore(
+    client: &Client,
+    audit: &Audit,
+    restorable: &RestorableInstance,
+    timeout: Duration,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:DeleteDBInstance",
+            &[
+                ("db_instance_identifier", &restorable.id),
+                ("final_db_snapshot_identifier", &restorable.snapshot),
+            ],
+            client
+                .delete_db_instance()
+                .db_instance_identifier(&restorable.id)
+                .skip_final_snapshot(false)
+                .final_db_snapshot_identifier(&restorable.snapshot)
+                .send(),
+        )
+        .await?;
+
+    let describe_snapshot = || {
+        client
+            .describe_db_snapshots()
+            .db_snapshot_identifier(&restorable.snapshot)
+            .send()
+    };
+    let states = wait_until("Final snapshots appearing", timeout, || async move {
+        // The snapshot is only created once the instance started deleting.
+        let status = match describe_snapshot().await {
+            Ok(output) => output
+                .db_snapshots()
+                .first()
+                .map(|snapshot| snapshot.status().unwrap_or("unknown").to_owned()),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_db_snapshot_not_found_fault()) =>
+            {
+                None
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Ok(vec![ResourceState::new(
+            &restorable.snapshot,
+            status.as_deref().unwrap_or("missing"),
+            status.is_some(),
+        )])
+    })
+    .await?;
+    if states.iter().any(|state| !state.converged) {
+        return Err(format!(
+            "Final snapshot {} of {} did not show up, it is not tagged for restore",
+            restorable.snapshot, restorable.id
+        )
+        .into());
+    }
+
+    let output = describe_snapshot().await?;
+    let arn = output
+        .db_snapshots()
+        .first()
+        .and_then(|snapshot| snapshot.db_snapshot_arn())
+        .ok_or_else(|| format!("Final snapshot {} has no ARN", restorable.snapshot))?;
+    audit
+        .record(
+            "rds:AddTagsToResource",
+            &[("resource_name", arn)],
+            client
+                .add_tags_to_resource()
+                .resource_name(arn)
+                .set_tags(Some(restorable.to_tags()))
+                .send(),
+        )
+        .await?;
     Ok(())
 }
 
-pub async fn stop_db_instance(client: &Client, db_instance_id: &str) -> AppResult<()> {
-    client
-        .stop_db_instance()
+// Tagged final snapshots of the cluster, the newest one per instance.
+pub async fn list_restorable_instances(
+    client: &Client,
+    cluster: &str,
+) -> AppResult<Vec<RestorableInstance>> {
+    let mut restorable: Vec<(DateTime<Utc>, RestorableInstance)> = Vec::new();
+    let mut db_snapshots_stream = client
+        .describe_db_snapshots()
+        .snapshot_type("manual")
+        .max_records(100)
+        .into_paginator()
+        .send();
+
+    while let Some(output) = db_snapshots_stream.next().await {
+        debug!("DB Snapshots: {:?}", output);
+        for snapshot in output?.db_snapshots() {
+            let (Some(id), Some(created)) = (
+                snapshot.db_snapshot_identifier(),
+                to_utc(snapshot.snapshot_create_time()),
+            ) else {
+                continue;
+            };
+            if !id.contains(cluster) || snapshot.status() != Some("available") {
+                continue;
+            }
+            let Some(instance) = RestorableInstance::from_tags(id, snapshot.tag_list()) else {
+                continue;
+            };
+            match restorable
+                .iter_mut()
+                .find(|(_, other)| other.id == instance.id)
+            {
+                Some(other) if other.0 < created => *other = (created, instance),
+                Some(_) => {}
+                None => restorable.push((created, instance)),
+            }
+        }
+    }
+    Ok(restorable
+        .into_iter()
+        .map(|(_, instance)| instance)
+        .collect())
+}
+
+pub async fn db_instance_exists(client: &Client, db_instance_id: &str) -> AppResult<bool> {
+    match client
+        .describe_db_instances()
         .db_instance_identifier(db_instance_id)
         .send()
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(err)
+            if err
+                .as_service_error()
+                .is_some_and(|e| e.is_db_instance_not_found_fault()) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub async fn enable_deletion_protection(
+    client: &Client,
+    audit: &Audit,
+    db_instance_id: &str,
+) -> AppResult<()> {
+    audit
+        .record(
+            "rds:ModifyDBInstance",
+            &[
+                ("db_instance_identifier", db_instance_id),
+                ("deletion_protection", "true"),
+            ],
+            client
+                .modify_db_instance()
+                .db_instance_identifier(db_instance_id)
+                .deletion_protection(true)
+                .apply_immediately(true)
+                .send(),
+        )
         .await?;
     Ok(())
 }
+
+// Restores the instance from its tagged final snapshot, waits until it is available and
+// protects it from deletion again. Returns the state the instance reached.
+pub async fn restore_db_instance(
+    client: &Client,
+    audit: &Audit,
+    restorable: &RestorableInstance,
+    timeout: Duration,
+) -> AppResult<ResourceState> {
+    audit
+        .record(
+            "rds:RestoreDBInstanceFromDBSnapshot",
+            &[
+                ("db_instance_identifier", &restorable.id),
+                ("db_snapshot_identifier", &restorable.snapshot),
+                ("db_instance_class", &restorable.instance_class),
+            ],
+            client
+                .restore_db_instance_from_db_snapshot()
+                .db_instance_identifier(&restorable.id)
+                .db_snapshot_identifier(&restorable.snapshot)
+                .db_instance_class(&restorable.instance_class)
+                .set_db_subnet_group_name(restorable.subnet_group.clone())
+                .set_db_parameter_group_name(restorable.parameter_group.clone())
+                .set_option_group_name(restorable.option_group.clone())
+                .set_vpc_security_group_ids(Some(restorable.security_groups.clone()))
+                .multi_az(restorable.multi_az)
+                .send(),
+        )
+        .await?;
+
+    let ids = [restorable.id.clone()];
+    let states = wait_until("RDS instances restoring", timeout, || {
+        describe_db_instance_states(client, &ids, "available")
+    })
+    .await?;
+    let state = states
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("DB instance {} not found", restorable.id))?;
+
+    if state.converged {
+        enable_deletion_protection(client, audit, &restorable.id).await?;
+    }
+    Ok(state)
+}
+
+// A snapshot of an instance or an Aurora cluster.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DbSnapshot {
+    pub id: String,
+    pub arn: String,
+    pub source: String,
+    pub cluster_snapshot: bool,
+    pub created: DateTime<Utc>,
+    pub size_gib: i32,
+    pub encrypted: bool,
+    pub keep: bool,
+}
+
+impl Tabular for DbSnapshot {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "SNAPSHOT",
+            "SOURCE",
+            "CREATED",
+            "SIZE GIB",
+            "ENCRYPTED",
+            "KEEP",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.source.clone(),
+            self.created.format("%Y-%m-%d %H:%M").to_string(),
+            self.size_gib.to_string(),
+            self.encrypted.to_string(),
+            self.keep.to_string(),
+        ]
+    }
+}
+
+fn to_utc(timestamp: Option<&aws_sdk_rds::primitives::DateTime>) -> Option<DateTime<Utc>> {
+    timestamp.and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos()))
+}
+
+// Snapshots tagged `keep=true` are never pruned.
+fn tagged_keep(tags: &[Tag]) -> bool {
+    tags.iter()
+        .any(|tag| tag.key() == Some("keep") && tag.value() == Some("true"))
+}
+
+fn from_db_snapshot(snapshot: &aws_sdk_rds::types::DbSnapshot) -> Option<DbSnapshot> {
+    Some(DbSnapshot {
+        id: snapshot.db_snapshot_identifier()?.to_owned(),
+        arn: snapshot.db_snapshot_arn()?.to_owned(),
+        source: snapshot.db_instance_identifier()?.to_owned(),
+        cluster_snapshot: false,
+        created: to_utc(snapshot.snapshot_create_time())?,
+        size_gib: snapshot.allocated_storage().unwrap_or_default(),
+        encrypted: snapshot.encrypted().unwrap_or_default(),
+        keep: tagged_keep(snapshot.tag_list()),
+    })
+}
+
+fn from_db_cluster_snapshot(
+    snapshot: &aws_sdk_rds::types::DbClusterSnapshot,
+) -> Option<DbSnapshot> {
+    Some(DbSnapshot {
+        id: snapshot.db_cluster_snapshot_identifier()?.to_owned(),
+        arn: snapshot.db_cluster_snapshot_arn()?.to_owned(),
+        source: snapshot.db_cluster_identifier()?.to_owned(),
+        cluster_snapshot: true,
+        created: to_utc(snapshot.snapshot_create_time())?,
+        size_gib: snapshot.allocated_storage().unwrap_or_default(),
+        encrypted: snapshot.storage_encrypted().unwrap_or_default(),
+        keep: tagged_keep(snapshot.tag_list()),
+    })
+}
+
+// Available manual snapshots of instances and clusters whose name contains the cluster.
+pub async fn list_manual_snapshots(client: &Client, cluster: &str) -> AppResult<Vec<DbSnapshot>> {
+    let mut snapshots = Vec::new();
+
+    let mut db_snapshots_stream = client
+        .describe_db_snapshots()
+        .snapshot_type("manual")
+        .max_records(100)
+        .into_paginator()
+        .send();
+    while let Some(output) = db_snapshots_stream.next().await {
+        debug!("DB Snapshots: {:?}", output);
+        snapshots.extend(
+            output?
+                .db_snapshots()
+                .iter()
+                .filter(|snapshot| snapshot.status() == Some("available"))
+                .filter_map(from_db_snapshot)
+                .filter(|snapshot| snapshot.id.contains(cluster)),
+        );
+    }
+
+    let mut db_cluster_snapshots_stream = client
+        .describe_db_cluster_snapshots()
+        .snapshot_type("manual")
+        .max_records(100)
+        .into_paginator()
+        .send();
+    while let Some(output) = db_cluster_snapshots_stream.next().await {
+        debug!("DB Cluster Snapshots: {:?}", output);
+        snapshots.extend(
+            output?
+                .db_cluster_snapshots()
+                .iter()
+                .filter(|snapshot| snapshot.status() == Some("available"))
+                .filter_map(from_db_cluster_snapshot)
+                .filter(|snapshot| snapshot.id.contains(cluster)),
+        );
+    }
+
+    Ok(snapshots)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retention {
+    pub keep_last: usize,
+    pub keep_days: Option<i64>,
+}
+
+impl Retention {
+    // Snapshots past the retention: older than the newest `keep_last` of their source and
+    // than `keep_days`, and not tagged to be kept.
+    pub fn expired<'a>(
+        &self,
+        snapshots: &'a [DbSnapshot],
+        now: DateTime<Utc>,
+    ) -> Vec<&'a DbSnapshot> {
+        snapshots
+            .iter()
+            .filter(|snapshot| {
+                let newer = snapshots
+                    .iter()
+                    .filter(|other| {
+                        other.source == snapshot.source
+                            && other.cluster_snapshot == snapshot.cluster_snapshot
+                            && other.created > snapshot.created
+                    })
+                    .count();
+                let recent = self
+                    .keep_days
+                    .is_some_and(|days| now - snapshot.created < chrono::Duration::days(days));
+                !snapshot.keep && newer >= self.keep_last && !recent
+            })
+            .collect()
+    }
+}
+
+pub async fn delete_snapshot(
+    client: &Client,
+    audit: &Audit,
+    snapshot: &DbSnapshot,
+) -> AppResult<()> {
+    if snapshot.cluster_snapshot {
+        audit
+            .record(
+                "rds:DeleteDBClusterSnapshot",
+                &[("db_cluster_snapshot_identifier", &snapshot.id)],
+                client
+                    .delete_db_cluster_snapshot()
+                    .db_cluster_snapshot_identifier(&snapshot.id)
+                    .send(),
+            )
+            .await?;
+    } else {
+        audit
+            .record(
+                "rds:DeleteDBSnapshot",
+                &[("db_snapshot_identifier", &snapshot.id)],
+                client
+                    .delete_db_snapshot()
+                    .db_snapshot_identifier(&snapshot.id)
+                    .send(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+// Newest available snapshot of an instance or a cluster, automated or manual.
+pub async fn latest_snapshot(
+    client: &Client,
+    source: &str,
+    cluster_snapshot: bool,
+) -> AppResult<Option<DbSnapshot>> {
+    let mut snapshots = Vec::new();
+
+    if cluster_snapshot {
+        let mut db_cluster_snapshots_stream = client
+            .describe_db_cluster_snapshots()
+            .db_cluster_identifier(source)
+            .max_records(100)
+            .into_paginator()
+            .send();
+        while let Some(output) = db_cluster_snapshots_stream.next().await {
+            debug!("DB Cluster Snapshots: {:?}", output);
+            snapshots.extend(
+                output?
+                    .db_cluster_snapshots()
+                    .iter()
+                    .filter(|snapshot| snapshot.status() == Some("available"))
+                    .filter_map(from_db_cluster_snapshot),
+            );
+        }
+    } else {
+        let mut db_snapshots_stream = client
+            .describe_db_snapshots()
+            .db_instance_identifier(source)
+            .max_records(100)
+            .into_paginator()
+            .send();
+        while let Some(output) = db_snapshots_stream.next().await {
+            debug!("DB Snapshots: {:?}", output);
+            snapshots.extend(
+                output?
+                    .db_snapshots()
+                    .iter()
+                    .filter(|snapshot| snapshot.status() == Some("available"))
+                    .filter_map(from_db_snapshot),
+            );
+        }
+    }
+
+    Ok(snapshots
+        .into_iter()
+        .max_by_key(|snapshot| snapshot.created))
+}
+
+// Copies a snapshot into the region of the client and returns the ARN of the copy.
+// A snapshot of another region is given by ARN with its `source_region`, an encrypted one
+// is re-encrypted with `kms_key_id`, or the default key of the region without one.
+pub async fn copy_snapshot(
+    client: &Client,
+    audit: &Audit,
+    source_arn: &str,
+    target_id: &str,
+    cluster_snapshot: bool,
+    kms_key_id: Option<&str>,
+    source_region: Option<&str>,
+) -> AppResult<String> {
+    let arn = if cluster_snapshot {
+        let output = audit
+            .record(
+                "rds:CopyDBClusterSnapshot",
+                &[
+                    ("source_db_cluster_snapshot_identifier", source_arn),
+                    ("target_db_cluster_snapshot_identifier", target_id),
+                    ("kms_key_id", kms_key_id.unwrap_or_default()),
+                ],
+                client
+                    .copy_db_cluster_snapshot()
+                    .source_db_cluster_snapshot_identifier(source_arn)
+                    .target_db_cluster_snapshot_identifier(target_id)
+                    .set_kms_key_id(kms_key_id.map(str::to_owned))
+                    .set_source_region(source_region.map(str::to_owned))
+                    .copy_tags(true)
+                    .send(),
+            )
+            .await?;
+        output
+            .db_cluster_snapshot()
+            .and_then(|snapshot| snapshot.db_cluster_snapshot_arn())
+            .map(str::to_owned)
+    } else {
+        let output = audit
+            .record(
+                "rds:CopyDBSnapshot",
+                &[
+                    ("source_db_snapshot_identifier", source_arn),
+                    ("target_db_snapshot_identifier", target_id),
+                    ("kms_key_id", kms_key_id.unwrap_or_default()),
+                ],
+                client
+                    .copy_db_snapshot()
+                    .source_db_snapshot_identifier(source_arn)
+                    .target_db_snapshot_identifier(target_id)
+                    .set_kms_key_id(kms_key_id.map(str::to_owned))
+                    .set_source_region(source_region.map(str::to_owned))
+                    .copy_tags(true)
+                    .send(),
+            )
+            .await?;
+        output
+            .db_snapshot()
+            .and_then(|snapshot| snapshot.db_snapshot_arn())
+            .map(str::to_owned)
+    };
+    let arn = arn.ok_or_else(|| format!("No ARN returned for the copy {}", target_id))?;
+    Ok(arn)
+}
+
+// Allows the account to copy or restore a manual snapshot. Snapshots encrypted with the
+// default RDS key cannot be shared, they have to be copied with a customer managed key first.
+pub async fn share_snapshot(
+    client: &Client,
+    audit: &Audit,
+    snapshot_id: &str,
+    cluster_snapshot: bool,
+    account_id: &str,
+) -> AppResult<()> {
+    if cluster_snapshot {
+        audit
+            .record(
+                "rds:ModifyDBClusterSnapshotAttribute",
+                &[
+                    ("db_cluster_snapshot_identifier", snapshot_id),
+                    ("attribute_name", "restore"),
+                    ("values_to_add", account_id),
+                ],
+                client
+                    .modify_db_cluster_snapshot_attribute()
+                    .db_cluster_snapshot_identifier(snapshot_id)
+                    .attribute_name("restore")
+                    .values_to_add(account_id)
+                    .send(),
+            )
+            .await?;
+    } else {
+        audit
+            .record(
+                "rds:ModifyDBSnapshotAttribute",
+                &[
+                    ("db_snapshot_identifier", snapshot_id),
+                    ("attribute_name", "restore"),
+                    ("values_to_add", account_id),
+                ],
+                client
+                    .modify_db_snapshot_attribute()
+                    .db_snapshot_identifier(snapshot_id)
+                    .attribute_name("restore")
+                    .values_to_add(account_id)
+                    .send(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+// Status and copy progress of a snapshot, a snapshot which does not exist yet is `missing`.
+async fn describe_snapshot_state(
+    client: &Client,
+    snapshot_id: &str,
+    cluster_snapshot: bool,
+) -> AppResult<ResourceState> {
+    let status = if cluster_snapshot {
+        match client
+            .describe_db_cluster_snapshots()
+            .db_cluster_snapshot_identifier(snapshot_id)
+            .send()
+            .await
+        {
+            Ok(output) => output.db_cluster_snapshots().first().map(|snapshot| {
+                (
+                    snapshot.status().unwrap_or("unknown").to_owned(),
+                    snapshot.percent_progress(),
+                )
+            }),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_db_cluster_snapshot_not_found_fault()) =>
+            {
+                None
+            }
+            Err(err) => return Err(err.into()),
+        }
+    } else {
+        match client
+            .describe_db_snapshots()
+            .db_snapshot_identifier(snapshot_id)
+            .send()
+            .await
+        {
+            Ok(output) => output.db_snapshots().first().map(|snapshot| {
+                (
+                    snapshot.status().unwrap_or("unknown").to_owned(),
+                    snapshot.percent_progress(),
+                )
+            }),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_db_snapshot_not_found_fault()) =>
+            {
+                None
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    Ok(match status {
+        Some((status, _)) if status == "available" => ResourceState::new(snapshot_id, status, true),
+        Some((status, Some(progress))) => {
+            ResourceState::new(snapshot_id, format!("{} {}%", status, progress), false)
+        }
+        Some((status, None)) => ResourceState::new(snapshot_id, status, false),
+        None => ResourceState::new(snapshot_id, "missing", false),
+    })
+}
+
+// Copies across regions take as long as the snapshot is large, so the timeout should allow it.
+pub async fn wait_for_snapshot_available(
+    client: &Client,
+    snapshot_id: &str,
+    cluster_snapshot: bool,
+    timeout: Duration,
+) -> AppResult<ResourceState> {
+    let states = wait_until("RDS snapshots copying", timeout, || async {
+        Ok(vec![
+            describe_snapshot_state(client, snapshot_id, cluster_snapshot).await?,
+        ])
+    })
+    .await?;
+    let state = states
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("DB snapshot {} not found", snapshot_id))?;
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_rds::operation::describe_db_clusters::DescribeDbClustersOutput;
+    use aws_sdk_rds::operation::describe_db_instances::DescribeDbInstancesOutput;
+    use aws_sdk_rds::operation::describe_db_snapshots::DescribeDbSnapshotsOutput;
+    use aws_sdk_rds::primitives::DateTime as SdkDateTime;
+    use aws_sdk_rds::types::DbInstance as SdkDbInstance;
+    use aws_sdk_rds::types::DbSnapshot as SdkDbSnapshot;
+    use aws_sdk_rds::types::{DbCluster as SdkDbCluster, DbClusterMember};
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+
+    fn db_instance(id: &str, status: &str) -> SdkDbInstance {
+        SdkDbInstance::builder()
+            .db_instance_identifier(id)
+            .db_instance_status(status)
+            .engine("postgres")
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_list_db_instances_by_status() {
+        let rule = mock!(Client::describe_db_instances).then_output(|| {
+            DescribeDbInstancesOutput::builder()
+                .db_instances(db_instance("app-postgres", "available"))
+                .db_instances(db_instance("app-postgres-old", "deleting"))
+                .db_instances(db_instance("app-postgres-replica", "stopped"))
+                .build()
+        });
+        let client = mock_client!(aws_sdk_rds, RuleMode::Sequential, [&rule]);
+
+        let db_instances = list_db_instances(&client, "app").await.unwrap();
+        let ids: Vec<_> = db_instances.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["app-postgres", "app-postgres-replica"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_db_clusters_with_members() {
+        let db_cluster = |id: &str, status: &str| {
+            SdkDbCluster::builder()
+                .db_cluster_identifier(id)
+                .status(status)
+                .engine("aurora-postgresql")
+                .db_cluster_members(
+                    DbClusterMember::builder()
+                        .db_instance_identifier(format!("{}-1", id))
+                        .build(),
+                )
+                .build()
+        };
+        let rule = mock!(Client::describe_db_clusters).then_output(move || {
+            DescribeDbClustersOutput::builder()
+                .db_clusters(db_cluster("app-aurora", "available"))
+                .db_clusters(db_cluster("app-aurora-old", "deleting"))
+                .db_clusters(db_cluster("web-aurora", "available"))
+                .build()
+        });
+        let client = mock_client!(aws_sdk_rds, RuleMode::Sequential, [&rule]);
+
+        let db_clusters = list_db_clusters(&client, "app").await.unwrap();
+        assert_eq!(
+            db_clusters,
+            vec![DbCluster {
+                id: "app-aurora".to_owned(),
+                status: "available".to_owned(),
+                engine: Some("aurora-postgresql".to_owned()),
+                members: vec!["app-aurora-1".to_owned()],
+            }]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_db_instances_stopped() {
+        let stopping = mock!(Client::describe_db_instances).then_output(|| {
+            DescribeDbInstancesOutput::builder()
+                .db_instances(db_instance("app-postgres", "stopping"))
+                .build()
+        });
+        let stopped = mock!(Client::describe_db_instances).then_output(|| {
+            DescribeDbInstancesOutput::builder()
+                .db_instances(db_instance("app-postgres", "stopped"))
+                .build()
+        });
+        let client = mock_client!(aws_sdk_rds, RuleMode::Sequential, [&stopping, &stopped]);
+
+        let ids = vec!["app-postgres".to_owned()];
+        let states = wait_for_db_instances_stopped(&client, &ids, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(stopped.num_calls(), 1);
+        assert_eq!(
+            states,
+            vec![ResourceState::new("app-postgres", "stopped", true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_latest_snapshot() {
+        let db_snapshot = |id: &str, status: &str, created: i64| {
+            SdkDbSnapshot::builder()
+                .db_snapshot_identifier(id)
+                .db_snapshot_arn(format!(
+                    "arn:aws:rds:eu-central-1:123456789012:snapshot:{}",
+                    id
+                ))
+                .db_instance_identifier("app-postgres")
+                .snapshot_type(if id.starts_with("rds:") {
+                    "automated"
+                } else {
+                    "manual"
+                })
+                .status(status)
+                .snapshot_create_time(SdkDateTime::from_secs(created))
+                .build()
+        };
+        let rule = mock!(Client::describe_db_snapshots).then_output(move || {
+            DescribeDbSnapshotsOutput::builder()
+                .db_snapshots(db_snapshot("rds:app-postgres-2026-10-17", "available", 100))
+                .db_snapshots(db_snapshot(
+                    "app-postgres-2026-10-18-06-00",
+                    "available",
+                    200,
+                ))
+                .db_snapshots(db_snapshot("rds:app-postgres-2026-10-19", "creating", 300))
+                .build()
+        });
+        let client = mock_client!(aws_sdk_rds, RuleMode::Sequential, [&rule]);
+
+        let latest = latest_snapshot(&client, "app-postgres", false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.id, "app-postgres-2026-10-18-06-00");
+        assert!(!latest.cluster_snapshot);
+    }
+
+    #[test]
+    fn test_retention_expired() {
+        let now = Utc::now();
+        let snapshot = |id: &str, days: i64, keep: bool| DbSnapshot {
+            id: id.to_owned(),
+            arn: format!("arn:aws:rds:eu-central-1:123456789012:snapshot:{}", id),
+            source: "app-postgres".to_owned(),
+            cluster_snapshot: false,
+            created: now - chrono::Duration::days(days),
+            size_gib: 20,
+            encrypted: true,
+            keep,
+        };
+        let snapshots = vec![
+            snapshot("app-postgres-1", 1, false),
+            snapshot("app-postgres-2", 10, false),
+            snapshot("app-postgres-3", 20, false),
+            snapshot("app-postgres-4", 40, true),
+            snapshot("app-postgres-5", 50, false),
+        ];
+
+        let retention = Retention {
+            keep_last: 1,
+            keep_days: Some(14),
+        };
+        let ids: Vec<_> = retention
+            .expired(&snapshots, now)
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["app-postgres-3", "app-postgres-5"]);
+
+        let retention = Retention {
+            keep_last: 3,
+            keep_days: None,
+        };
+        let ids: Vec<_> = retention
+            .expired(&snapshots, now)
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["app-postgres-5"]);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::AppResult;
+use aws_smithy_runtime_api::client::result::SdkError;
+use aws_smithy_runtime_api::http::Response;
+use aws_smithy_types::error::metadata::ProvideErrorMetadata;
+use log::debug;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+// Error codes returned by AWS services when a request is throttled.
+const THROTTLING_CODES: &[&str] = &[
+    "Throttling",
+    "ThrottlingException",
+    "ThrottledException",
+    "RequestThrottled",
+    "RequestThrottledException",
+    "RequestLimitExceeded",
+    "TooManyRequestsException",
+    "ProvisionedThroughputExceededException",
+    "SlowDown",
+    "BandwidthLimitExceeded",
+];
+
+// Error codes which mean the resource is busy and the same call may succeed later.
+const CONFLICT_CODES: &[&str] = &[
+    "ConflictException",
+    "ConcurrentModificationException",
+    "OperationAbortedException",
+    "PriorRequestNotComplete",
+    "IncorrectState",
+    "IncorrectInstanceState",
+    "DependencyViolation",
+    "ResourceInUse",
+    "ResourceInUseException",
+    "UpdateInProgressException",
+];
+
+// Error codes for transient server side failures.
+const TRANSIENT_CODES: &[&str] = &[
+    "InternalError",
+    "InternalFailure",
+    "InternalServerError",
+    "ServerException",
+    "ServiceUnavailable",
+    "ServiceUnavailableException",
+    "RequestTimeout",
+    "RequestTimeoutException",
+];
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            deadline: Some(Duration::from_secs(600)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    // Upper bound of the sleep before the given retry (1-based), before jitter.
+    fn backoff_ceiling(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff)
+    }
+
+    // Full jitter: a random duration between zero and the exponential ceiling.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self.backoff_ceiling(attempt).as_millis() as u64;
+        Duration::from_millis(fastrand::u64(0..=ceiling))
+    }
+}
+
+pub fn is_retryable_code(code: &str) -> bool {
+    THROTTLING_CODES.contains(&code)
+        || CONFLICT_CODES.contains(&code)
+        || TRANSIENT_CODES.contains(&code)
+}
+
+pub fn is_retryable<E: ProvideErrorMetadata>(err: &SdkError<E, Response>) -> bool {
+    match err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => {
+            true
+        }
+        SdkError::ServiceError(context) => {
+            let status = context.raw().status().as_u16();
+            status == 429
+                || status >= 500
+                || context.err().code().is_some_and(is_retryable_code)
+        }
+        _ => false,
+    }
+}
+
+pub async fn retry<T, E, F, Fut>(policy: &RetryPolicy, operation: &str, mut f: F) -> AppResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, SdkError<E, Response>>>,
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    let started = Instant::now();
+    let mut attempt = 1;
+
+    loop {
+        let err = match f().await {
+            Ok(output) => return Ok(output),
+            Err(err) => err,
+        };
+        debug!("{} failed on attempt {}: {:?}", operation, attempt, err);
+
+        if !is_retryable(&err) || attempt >= policy.max_attempts {
+            return Err(err.into());
+        }
+
+        let delay = policy.backoff(attempt);
+        if let Some(deadline) = policy.deadline
+            && started.elapsed() + delay > deadline
+        {
+            return Err(err.into());
+        }
+
+        eprintln!(
+            "{} failed ({}). Retrying in {} ms... (attempt {}/{})",
+            operation,
+            err.code().unwrap_or("transient error"),
+            delay.as_millis(),
+            attempt,
+            policy.max_attempts
+        );
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_ceiling() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_secs(1), Duration::from_secs(10));
+        assert_eq!(policy.backoff_ceiling(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff_ceiling(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff_ceiling(5), Duration::from_secs(10));
+        assert_eq!(policy.backoff_ceiling(64), Duration::from_secs(10));
+        assert!(policy.backoff(3) <= Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_retryable_codes() {
+        assert!(is_retryable_code("ThrottlingException"));
+        assert!(is_retryable_code("ConflictException"));
+        assert!(is_retryable_code("ServiceUnavailable"));
+        assert!(!is_retryable_code("AccessDenied"));
+        assert!(!is_retryable_code("AccessDeniedException"));
+        assert!(!is_retryable_code("UnauthorizedOperation"));
+    }
+}
@@ -1,8 +1,48 @@
 use crate::AppResult;
+use crate::audit::Audit;
+use crate::output::Tabular;
 use aws_sdk_s3::Client as S3Client;
 use aws_sdk_s3::types::MetadataDirective;
 use log::debug;
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Bucket {
+    pub name: String,
+    pub region: Option<String>,
+}
+
+impl Tabular for Bucket {
+    fn headers() -> Vec<&'static str> {
+        vec!["BUCKET", "BUCKET REGION"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.name.clone(), self.region.clone().unwrap_or_default()]
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BucketCopy {
+    pub bucket: String,
+    pub objects: usize,
+    pub copied: usize,
+}
+
+impl Tabular for BucketCopy {
+    fn headers() -> Vec<&'static str> {
+        vec!["BUCKET", "OBJECTS", "COPIED"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.bucket.clone(),
+            self.objects.to_string(),
+            self.copied.to_string(),
+        ]
+    }
+}
 
 pub async fn save_bucket_policy(client: &S3Client, bucket: &str) -> AppResult<Option<String>> {
     // Save current bucket policy JSON
@@ -12,14 +52,35 @@ pub async fn save_bucket_policy(client: &S3Client, bucket: &str) -> AppResult<Op
     }
 }
 
-pub async fn restore_bucket_policy(client: &S3Client, bucket: &str, policy: &str) -> AppResult<()> {
-    client.delete_bucket_policy().bucket(bucket).send().await?;
+pub async fn delete_bucket_policy(client: &S3Client, audit: &Audit, bucket: &str) -> AppResult<()> {
+    audit
+        .record(
+            "s3:DeleteBucketPolicy",
+            &[("bucket", bucket)],
+            client.delete_bucket_policy().bucket(bucket).send(),
+        )
+        .await?;
+    Ok(())
+}
 
-    if let Err(e) = client
-        .put_bucket_policy()
-        .bucket(bucket)
-        .policy(policy)
-        .send()
+pub async fn restore_bucket_policy(
+    client: &S3Client,
+    audit: &Audit,
+    bucket: &str,
+    policy: &str,
+) -> AppResult<()> {
+    delete_bucket_policy(client, audit, bucket).await?;
+
+    if let Err(e) = audit
+        .record(
+            "s3:PutBucketPolicy",
+            &[("bucket", bucket), ("policy", policy)],
+            client
+                .put_bucket_policy()
+                .bucket(bucket)
+                .policy(policy)
+                .send(),
+        )
         .await
     {
         eprintln!("Failure restore {} bucket policy {}.", bucket, e);
@@ -28,13 +89,15 @@ pub async fn restore_bucket_policy(client: &S3Client, bucket: &str, policy: &str
     Ok(())
 }
 
-async fn list_filtered_buckets_internal<F>(client: &S3Client, filter: F) -> AppResult<Vec<String>>
+async fn list_filtered_buckets_internal<F>(client: &S3Client, filter: F) -> AppResult<Vec<Bucket>>
 where
     F: Fn(&str) -> bool + Send + Sync,
 {
-    let mut bucket_names: Vec<String> = Vec::new();
+    let mut bucket_list: Vec<Bucket> = Vec::new();
+    // Only buckets of the client region, so a fan-out over regions visits each bucket once
     let mut buckets_stream = client
         .list_buckets()
+        .set_bucket_region(client.config().region().map(ToString::to_string))
         .max_buckets(100)
         .into_paginator()
         .send();
@@ -42,24 +105,27 @@ where
     while let Some(buckets) = buckets_stream.next().await {
         debug!("Buckets: {:?}", buckets);
 
-        bucket_names.extend(buckets?.buckets().iter().filter_map(|b| {
+        bucket_list.extend(buckets?.buckets().iter().filter_map(|b| {
             let bucket_name = b.name()?;
             if filter(bucket_name) {
-                Some(bucket_name.to_owned())
+                Some(Bucket {
+                    name: bucket_name.to_owned(),
+                    region: b.bucket_region().map(ToOwned::to_owned),
+                })
             } else {
                 None
             }
         }));
     }
 
-    Ok(bucket_names)
+    Ok(bucket_list)
 }
 
 pub async fn get_buckets(
     client: &S3Client,
     include: &[String],
     exclude: &[String],
-) -> AppResult<Vec<String>> {
+) -> AppResult<Vec<Bucket>> {
     let include = include.to_vec();
     let exclude = exclude.to_vec();
 
@@ -71,7 +137,11 @@ pub async fn get_buckets(
     list_filtered_buckets_internal(client, filter).await
 }
 
-pub async fn copy_all_objects(client: &S3Client, bucket: &str) -> Result<usize, aws_sdk_s3::Error> {
+pub async fn copy_all_objects(
+    client: &S3Client,
+    audit: &Audit,
+    bucket: &str,
+) -> Result<BucketCopy, aws_sdk_s3::Error> {
     let paginator = client
         .list_objects_v2()
         .bucket(bucket)
@@ -103,7 +173,7 @@ pub async fn copy_all_objects(client: &S3Client, bucket: &str) -> Result<usize,
                         .is_none();
 
                     if no_gd_tag {
-                        if let Err(e) = copy_to_self(client, bucket, key).await {
+                        if let Err(e) = copy_to_self(client, audit, bucket, key).await {
                             eprintln!(
                                 "Error: file '{}' could not be copied to bucket '{}'. Details: {}",
                                 key, bucket, e
@@ -113,26 +183,43 @@ pub async fn copy_all_objects(client: &S3Client, bucket: &str) -> Result<usize,
                     }
                 }
                 if counter_total % 100 == 0 {
-                    println!("processed {} objects, copied {}", counter_total, counter);
+                    eprintln!(
+                        "{}: processed {} objects, copied {}",
+                        bucket, counter_total, counter
+                    );
                 }
             }
         }
     }
 
-    Ok(counter)
+    Ok(BucketCopy {
+        bucket: bucket.to_owned(),
+        objects: counter_total,
+        copied: counter,
+    })
 }
 
-pub async fn copy_to_self(client: &S3Client, bucket: &str, key: &str) -> AppResult<()> {
+pub async fn copy_to_self(
+    client: &S3Client,
+    audit: &Audit,
+    bucket: &str,
+    key: &str,
+) -> AppResult<()> {
     let encoded_key = utf8_percent_encode(key, NON_ALPHANUMERIC).to_string();
     let copy_source = format!("{}/{}", bucket, encoded_key);
 
-    client
-        .copy_object()
-        .bucket(bucket)
-        .key(key)
-        .copy_source(copy_source)
-        .metadata_directive(MetadataDirective::Copy)
-        .send()
+    audit
+        .record(
+            "s3:CopyObject",
+            &[("bucket", bucket), ("key", key)],
+            client
+                .copy_object()
+                .bucket(bucket)
+                .key(key)
+                .copy_source(&copy_source)
+                .metadata_directive(MetadataDirective::Copy)
+                .send(),
+        )
         .await?;
 
     Ok(())
@@ -1,13 +1,37 @@
 use crate::AppResult;
+use crate::output::Tabular;
 use aws_sdk_sesv2::{Client, types::SuppressedDestinationSummary};
 use chrono::{DateTime, Utc};
 use log::{debug, info};
-use std::{thread, time};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::time::sleep;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SuppressedDestination {
+    pub email: String,
+    pub reason: String,
+    pub last_update: String,
+}
+
+impl Tabular for SuppressedDestination {
+    fn headers() -> Vec<&'static str> {
+        vec!["EMAIL", "REASON", "LAST UPDATE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.email.clone(),
+            self.reason.clone(),
+            self.last_update.clone(),
+        ]
+    }
+}
 
 pub async fn get_suppression_list(
     sesv2_client: &Client,
     last_count_days: Option<u32>,
-) -> AppResult<Vec<(String, String, String)>> {
+) -> AppResult<Vec<SuppressedDestination>> {
     let mut sesv2_addresses_stream = sesv2_client
         .list_suppressed_destinations()
         .page_size(1000)
@@ -26,7 +50,7 @@ pub async fn get_suppression_list(
                 .iter()
                 .filter_map(|address| email_address_filter(last_count_days, &now, address)),
         );
-        thread::sleep(time::Duration::from_millis(1000));
+        sleep(Duration::from_millis(1000)).await;
     }
     Ok(emails)
 }
@@ -35,7 +59,7 @@ fn email_address_filter(
     last_count_days: Option<u32>,
     now: &DateTime<Utc>,
     address: &SuppressedDestinationSummary,
-) -> Option<(String, String, String)> {
+) -> Option<SuppressedDestination> {
     debug!("Address: {:?}", address);
     let timestamp = address.last_update_time();
     match last_count_days {
@@ -49,7 +73,7 @@ fn get_email_if_match_time_interval(
     address: &SuppressedDestinationSummary,
     timestamp: &aws_sdk_ec2::primitives::DateTime,
     last: u32,
-) -> Option<(String, String, String)> {
+) -> Option<SuppressedDestination> {
     let time_date = match DateTime::from_timestamp(timestamp.secs(), timestamp.subsec_nanos()) {
         Some(time_date) => time_date,
         None => {
@@ -70,10 +94,10 @@ fn get_email_if_match_time_interval(
 fn get_email_suppression_record(
     address: &SuppressedDestinationSummary,
     timestamp: &aws_sdk_ec2::primitives::DateTime,
-) -> Option<(String, String, String)> {
-    Some((
-        address.email_address().to_string(),
-        address.reason().to_string(),
-        timestamp.to_string(),
-    ))
+) -> Option<SuppressedDestination> {
+    Some(SuppressedDestination {
+        email: address.email_address().to_string(),
+        reason: address.reason().to_string(),
+        last_update: timestamp.to_string(),
+    })
 }
@@ -0,0 +1,435 @@
+use crate::AppResult;
+use crate::audit::Audit;
+use crate::ec2::{self, prefix_filter};
+use crate::output::Tabular;
+use aws_sdk_ec2::Client;
+use aws_sdk_ec2::types::{Filter, Tag, VolumeType};
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::Serialize;
+
+// Monthly prices in USD of us-east-1, other regions differ by a few percent, which is good
+// enough for an estimate. Storage is billed per provisioned GiB.
+const VOLUME_GB_MONTH: [(&str, f64); 7] = [
+    ("gp3", 0.08),
+    ("gp2", 0.10),
+    ("io1", 0.125),
+    ("io2", 0.125),
+    ("st1", 0.045),
+    ("sc1", 0.015),
+    ("standard", 0.05),
+];
+const PROVISIONED_IOPS_MONTH: f64 = 0.065;
+// gp3 includes 3000 IOPS and 125 MB/s, more is billed on top.
+const GP3_IOPS_MONTH: f64 = 0.005;
+const GP3_THROUGHPUT_MONTH: f64 = 0.04;
+// Snapshots are incremental, the volume size is the upper bound.
+const SNAPSHOT_GB_MONTH: f64 = 0.05;
+// Public IPv4 addresses cost 0.005 per hour, whether they are associated or not.
+const ELASTIC_IP_MONTH: f64 = 3.65;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OrphanKind {
+    Volume,
+    NetworkInterface,
+    ElasticIp,
+    Image,
+    Snapshot,
+}
+
+impl OrphanKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            OrphanKind::Volume => "volume",
+            OrphanKind::NetworkInterface => "network-interface",
+            OrphanKind::ElasticIp => "elastic-ip",
+            OrphanKind::Image => "image",
+            OrphanKind::Snapshot => "snapshot",
+        }
+    }
+}
+
+// A resource of the cluster nothing uses any more, `found` until it is deleted.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Orphan {
+    pub kind: OrphanKind,
+    pub id: String,
+    pub name: Option<String>,
+    pub size_gib: Option<i32>,
+    pub created: Option<DateTime<Utc>>,
+    // Snapshots deleted together with an image.
+    pub snapshot_ids: Vec<String>,
+    pub monthly_cost: f64,
+    pub action: &'static str,
+}
+
+impl Tabular for Orphan {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "KIND",
+            "ID",
+            "NAME",
+            "SIZE GIB",
+            "CREATED",
+            "MONTHLY USD",
+            "ACTION",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.kind.as_str().to_owned(),
+            self.id.clone(),
+            self.name.clone().unwrap_or_default(),
+            self.size_gib
+                .map(|size| size.to_string())
+                .unwrap_or_default(),
+            self.created
+                .map(|created| created.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default(),
+            format!("{:.2}", self.monthly_cost),
+            self.action.to_owned(),
+        ]
+    }
+}
+
+fn orphan(kind: OrphanKind, id: &str, tags: &[Tag]) -> Orphan {
+    Orphan {
+        kind,
+        id: id.to_owned(),
+        name: tag_value(tags, "Name").map(ToOwned::to_owned),
+        size_gib: None,
+        created: None,
+        snapshot_ids: Vec::new(),
+        monthly_cost: 0.0,
+        action: "found",
+    }
+}
+
+fn tag_value<'a>(tags: &'a [Tag], key: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|tag| tag.key() == Some(key))
+        .and_then(|tag| tag.value())
+}
+
+// Resources tagged `keep=true` are never swept, as with RDS snapshots.
+fn tagged_keep(tags: &[Tag]) -> bool {
+    tag_value(tags, "keep") == Some("true")
+}
+
+fn to_utc(timestamp: Option<&aws_sdk_ec2::primitives::DateTime>) -> Option<DateTime<Utc>> {
+    timestamp.and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos()))
+}
+
+fn volume_cost(
+    volume_type: Option<&VolumeType>,
+    size_gib: i32,
+    iops: Option<i32>,
+    throughput: Option<i32>,
+) -> f64 {
+    // Volume types missing from the table are not estimated.
+    let volume_type = volume_type.map_or("standard", VolumeType::as_str);
+    let gb_month = VOLUME_GB_MONTH
+        .iter()
+        .find(|(name, _)| *name == volume_type)
+        .map_or(0.0, |(_, price)| *price);
+    let storage = f64::from(size_gib) * gb_month;
+    let iops = f64::from(iops.unwrap_or_default());
+    let throughput = f64::from(throughput.unwrap_or_default());
+
+    storage
+        + match volume_type {
+            "io1" | "io2" => iops * PROVISIONED_IOPS_MONTH,
+            "gp3" => {
+                (iops - 3000.0).max(0.0) * GP3_IOPS_MONTH
+                    + (throughput - 125.0).max(0.0) * GP3_THROUGHPUT_MONTH
+            }
+            _ => 0.0,
+        }
+}
+
+async fn list_volumes(client: &Client, cluster: &str) -> AppResult<Vec<Orphan>> {
+    let mut orphans = Vec::new();
+    let mut volumes_stream = client
+        .describe_volumes()
+        .filters(prefix_filter(cluster))
+        .filters(Filter::builder().name("status").values("available").build())
+        .into_paginator()
+        .send();
+
+    while let Some(output) = volumes_stream.next().await {
+        for volume in output?.volumes() {
+            let Some(id) = volume.volume_id() else {
+                continue;
+            };
+            if tagged_keep(volume.tags()) {
+                continue;
+            }
+            let size_gib = volume.size().unwrap_or_default();
+            orphans.push(Orphan {
+                size_gib: Some(size_gib),
+                created: to_utc(volume.create_time()),
+                monthly_cost: volume_cost(
+                    volume.volume_type(),
+                    size_gib,
+                    volume.iops(),
+                    volume.throughput(),
+                ),
+                ..orphan(OrphanKind::Volume, id, volume.tags())
+            });
+        }
+    }
+    Ok(orphans)
+}
+
+// Interfaces managed by a service, e.g. Lambda, are deleted by the service itself.
+async fn list_network_interfaces(client: &Client, cluster: &str) -> AppResult<Vec<Orphan>> {
+    let mut orphans = Vec::new();
+    let mut interfaces_stream = client
+        .describe_network_interfaces()
+        .filters(prefix_filter(cluster))
+        .filters(Filter::builder().name("status").values("available").build())
+        .into_paginator()
+        .send();
+
+    while let Some(output) = interfaces_stream.next().await {
+        for interface in output?.network_interfaces() {
+            let Some(id) = interface.network_interface_id() else {
+                continue;
+            };
+            if interface.requester_managed() == Some(true) || tagged_keep(interface.tag_set()) {
+                continue;
+            }
+            let mut found = orphan(OrphanKind::NetworkInterface, id, interface.tag_set());
+            if found.name.is_none() {
+                found.name = interface.description().map(ToOwned::to_owned);
+            }
+            orphans.push(found);
+        }
+    }
+    Ok(orphans)
+}
+
+// Elastic IPs kept for NAT gateways which --scaleup recreates are not orphaned.
+async fn list_addresses(client: &Client, cluster: &str) -> AppResult<Vec<Orphan>> {
+    let kept: Vec<String> = ec2::list_nat_gateway_records(client, cluster)
+        .await?
+        .into_iter()
+        .filter_map(|record| record.allocation_id)
+        .collect();
+    let output = client
+        .describe_addresses()
+        .filters(prefix_filter(cluster))
+        .send()
+        .await?;
+
+    Ok(output
+        .addresses()
+        .iter()
+        .filter(|address| address.association_id().is_none() && !tagged_keep(address.tags()))
+        .filter_map(|address| {
+            let id = address.allocation_id()?;
+            if kept.iter().any(|kept| kept == id) {
+                return None;
+            }
+            let mut found = orphan(OrphanKind::ElasticIp, id, address.tags());
+            if found.name.is_none() {
+                found.name = address.public_ip().map(ToOwned::to_owned);
+            }
+            Some(Orphan {
+                monthly_cost: ELASTIC_IP_MONTH,
+                ..found
+            })
+        })
+        .collect())
+}
+
+// Images older than `created_before` with their snapshots, and snapshots older than that
+// which no image of the cluster uses. Snapshots of newer images stay with them.
+async fn list_images_and_snapshots(
+    client: &Client,
+    cluster: &str,
+    created_before: DateTime<Utc>,
+) -> AppResult<Vec<Orphan>> {
+    let images = client
+        .describe_images()
+        .owners("self")
+        .filters(prefix_filter(cluster))
+        .send()
+        .await?;
+    debug!("Images: {:?}", images);
+
+    let mut orphans = Vec::new();
+    let mut image_snapshot_ids: Vec<String> = Vec::new();
+    for image in images.images() {
+        let Some(id) = image.image_id() else {
+            continue;
+        };
+        let disks: Vec<(String, i32)> = image
+            .block_device_mappings()
+            .iter()
+            .filter_map(|mapping| {
+                let ebs = mapping.ebs()?;
+                Some((
+                    ebs.snapshot_id()?.to_owned(),
+                    ebs.volume_size().unwrap_or_default(),
+                ))
+            })
+            .collect();
+        image_snapshot_ids.extend(disks.iter().map(|(snapshot_id, _)| snapshot_id.clone()));
+
+        let created = image
+            .creation_date()
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.with_timezone(&Utc));
+        if created.is_none_or(|created| created >= created_before) || tagged_keep(image.tags()) {
+            continue;
+        }
+        let size_gib: i32 = disks.iter().map(|(_, size)| size).sum();
+        let mut found = orphan(OrphanKind::Image, id, image.tags());
+        if found.name.is_none() {
+            found.name = image.name().map(ToOwned::to_owned);
+        }
+        orphans.push(Orphan {
+            size_gib: Some(size_gib),
+            created,
+            snapshot_ids: disks
+                .into_iter()
+                .map(|(snapshot_id, _)| snapshot_id)
+                .collect(),
+            monthly_cost: f64::from(size_gib) * SNAPSHOT_GB_MONTH,
+            ..found
+        });
+    }
+
+    let mut snapshots_stream = client
+        .describe_snapshots()
+        .owner_ids("self")
+        .filters(prefix_filter(cluster))
+        .into_paginator()
+        .send();
+    while let Some(output) = snapshots_stream.next().await {
+        for snapshot in output?.snapshots() {
+            let Some(id) = snapshot.snapshot_id() else {
+                continue;
+            };
+            let created = to_utc(snapshot.start_time());
+            if image_snapshot_ids.iter().any(|used| used == id)
+                || created.is_none_or(|created| created >= created_before)
+                || tagged_keep(snapshot.tags())
+            {
+                continue;
+            }
+            let size_gib = snapshot.volume_size().unwrap_or_default();
+            let mut found = orphan(OrphanKind::Snapshot, id, snapshot.tags());
+            if found.name.is_none() {
+                found.name = snapshot
+                    .description()
+                    .filter(|description| !description.is_empty())
+                    .map(ToOwned::to_owned);
+            }
+            orphans.push(Orphan {
+                size_gib: Some(size_gib),
+                created,
+                monthly_cost: f64::from(size_gib) * SNAPSHOT_GB_MONTH,
+                ..found
+            });
+        }
+    }
+    Ok(orphans)
+}
+
+// Unattached volumes, detached network interfaces, unassociated Elastic IPs and images
+// and snapshots created before `created_before`, found by the `Prefix` tag of the cluster.
+pub async fn list_orphans(
+    client: &Client,
+    cluster: &str,
+    created_before: DateTime<Utc>,
+) -> AppResult<Vec<Orphan>> {
+    let (volumes, network_interfaces, addresses, images_and_snapshots) = futures::try_join!(
+        list_volumes(client, cluster),
+        list_network_interfaces(client, cluster),
+        list_addresses(client, cluster),
+        list_images_and_snapshots(client, cluster, created_before),
+    )?;
+
+    let mut orphans = volumes;
+    orphans.extend(network_interfaces);
+    orphans.extend(addresses);
+    orphans.extend(images_and_snapshots);
+    Ok(orphans)
+}
+
+// An image is deregistered before its snapshots, which are in use until then.
+pub async fn delete_orphan(client: &Client, audit: &Audit, orphan: &Orphan) -> AppResult<()> {
+    let id = orphan.id.as_str();
+    match orphan.kind {
+        OrphanKind::Volume => {
+            audit
+                .record(
+                    "ec2:DeleteVolume",
+                    &[("volume_id", id)],
+                    client.delete_volume().volume_id(id).send(),
+                )
+                .await?;
+        }
+        OrphanKind::NetworkInterface => {
+            audit
+                .record(
+                    "ec2:DeleteNetworkInterface",
+                    &[("network_interface_id", id)],
+                    client
+                        .delete_network_interface()
+                        .network_interface_id(id)
+                        .send(),
+                )
+                .await?;
+        }
+        OrphanKind::ElasticIp => ec2::release_address(client, audit, id).await?,
+        OrphanKind::Image => {
+            audit
+                .record(
+                    "ec2:DeregisterImage",
+                    &[("image_id", id)],
+                    client.deregister_image().image_id(id).send(),
+                )
+                .await?;
+            for snapshot_id in &orphan.snapshot_ids {
+                delete_snapshot(client, audit, snapshot_id).await?;
+            }
+        }
+        OrphanKind::Snapshot => delete_snapshot(client, audit, id).await?,
+    }
+    Ok(())
+}
+
+async fn delete_snapshot(client: &Client, audit: &Audit, snapshot_id: &str) -> AppResult<()> {
+    audit
+        .record(
+            "ec2:DeleteSnapshot",
+            &[("snapshot_id", snapshot_id)],
+            client.delete_snapshot().snapshot_id(snapshot_id).send(),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cost(volume_type: Option<VolumeType>, size: i32, iops: i32, throughput: i32) -> String {
+        let cost = volume_cost(volume_type.as_ref(), size, Some(iops), Some(throughput));
+        format!("{:.2}", cost)
+    }
+
+    #[test]
+    fn test_volume_cost() {
+        assert_eq!(cost(Some(VolumeType::Gp2), 100, 300, 0), "10.00");
+        assert_eq!(cost(Some(VolumeType::Gp3), 100, 3000, 125), "8.00");
+        assert_eq!(cost(Some(VolumeType::Gp3), 100, 4000, 225), "17.00");
+        assert_eq!(cost(Some(VolumeType::Io2), 10, 1000, 0), "66.25");
+        assert_eq!(cost(None, 10, 0, 0), "0.50");
+    }
+}
@@ -20,6 +20,12 @@ pub(crate) fn sanitize_string(input: &str) -> String {
         .to_string()
 }
 
+// `<id>-YYYY-MM-DD-HH-MM`, the name of snapshots taken when a resource is deleted.
+pub(crate) fn final_snapshot_name(id: &str) -> String {
+    let now_formatted = chrono::Utc::now().format("%Y-%m-%d-%H-%M");
+    sanitize_string(&format!("{}-{}", id, now_formatted))
+}
+
 #[cfg(test)]
 mod tests {
     use super::sanitize_string;
@@ -0,0 +1,106 @@
+use crate::AppResult;
+use log::{debug, warn};
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::{Instant, sleep};
+
+// Time between two describe calls while waiting.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+// Last observed state of a resource which is waited on.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResourceState {
+    pub id: String,
+    pub state: String,
+    pub converged: bool,
+}
+
+impl ResourceState {
+    pub fn new(id: impl Into<String>, state: impl Into<String>, converged: bool) -> Self {
+        Self {
+            id: id.into(),
+            state: state.into(),
+            converged,
+        }
+    }
+}
+
+// Polls until every resource converged or the timeout passed, and returns the last states.
+// Running out of time is not an error, the caller decides from the states.
+pub async fn wait_until<F, Fut>(
+    what: &str,
+    timeout: Duration,
+    mut poll: F,
+) -> AppResult<Vec<ResourceState>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AppResult<Vec<ResourceState>>>,
+{
+    let started = Instant::now();
+
+    loop {
+        let states = poll().await?;
+        debug!("{}: {:?}", what, states);
+
+        let pending: Vec<String> = states
+            .iter()
+            .filter(|state| !state.converged)
+            .map(|state| format!("{} ({})", state.id, state.state))
+            .collect();
+        if pending.is_empty() {
+            return Ok(states);
+        }
+
+        let elapsed = started.elapsed();
+        if elapsed >= timeout {
+            warn!(
+                "{}: gave up after {} s, still pending: {}",
+                what,
+                timeout.as_secs(),
+                pending.join(", ")
+            );
+            return Ok(states);
+        }
+
+        eprintln!(
+            "{}: {} of {} pending after {} s: {}",
+            what,
+            pending.len(),
+            states.len(),
+            elapsed.as_secs(),
+            pending.join(", ")
+        );
+        sleep(POLL_INTERVAL.min(timeout - elapsed)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_until() {
+        let mut polls = 0;
+        let states = wait_until("Instances", Duration::from_secs(600), || {
+            polls += 1;
+            let converged = polls == 3;
+            async move { Ok(vec![ResourceState::new("i-1", "stopping", converged)]) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(polls, 3);
+        assert!(states[0].converged);
+
+        let mut polls = 0;
+        let states = wait_until("Instances", Duration::from_secs(40), || {
+            polls += 1;
+            async { Ok(vec![ResourceState::new("i-1", "stopping", false)]) }
+        })
+        .await
+        .unwrap();
+        // Polled at 0, 15, 30 and once more when the timeout ran out at 40 seconds.
+        assert_eq!(polls, 4);
+        assert_eq!(states, vec![ResourceState::new("i-1", "stopping", false)]);
+    }
+}